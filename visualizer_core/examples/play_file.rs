@@ -0,0 +1,24 @@
+use visualizer_core::{Controller, Pacing, Settings};
+
+/// Play a WAV or FLAC file through an effect without any sound card.
+///
+/// Usage: cargo run --example play_file -- <path> [effect] [--fast]
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().expect("Usage: play_file <path> [effect] [--fast]");
+    let rest = args.collect::<Vec<String>>();
+    let pacing = if rest.iter().any(|it| it == "--fast") { Pacing::AsFastAsPossible } else { Pacing::RealTime };
+
+    let mut controller = Controller::new();
+    let effect = rest.iter()
+        .find_map(|name| controller.get_effects().into_iter().find(|it| it == name))
+        .unwrap_or("Melbank");
+
+    let rx = controller.open_file(path, pacing, effect, Settings::default(), [255, 255, 255])
+        .expect("Failed to open the audio file");
+
+    // Count the processed frames. The channel is closed, when the whole file was processed
+    let frames = rx.iter().count();
+
+    println!("Processed {} frames with the effect {}", frames, effect);
+}
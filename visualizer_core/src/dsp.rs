@@ -1,145 +1,386 @@
 use std::sync::{Arc, Mutex};
 
-use hann_rs::get_hann_window;
-use realfft::num_traits::{Pow};
-use realfft::RealFftPlanner;
-
 use super::stream;
+use super::stream::{ChannelMode, Filterbank};
+use super::stream::channel::Sender;
 use super::effects::AudioData;
-use super::math::array_product;
 
 // Modules
-mod melbank;
-mod smoothing;
+pub mod melbank;
+pub mod smoothing;
 mod detection;
+mod framer;
+mod context;
+mod window;
+mod constant_q;
+mod chroma;
+mod beat;
+mod percussion;
+mod hpss;
+mod agc;
+mod weighting;
+mod equalizer;
+mod noise;
 
 // Re-export all utilities for the effects
-pub use melbank::compute_mel_matrix;
-pub use smoothing::ExponentialFilter;
-pub use detection::PeakDetector;
+pub use melbank::{FilterNormalization, FrequencyScale, MelbankCache};
+pub use smoothing::{ExponentialFilter, Smoothing, SmoothingMethod};
+pub use detection::{Onset, OnsetDetector, PeakDetector};
+pub use framer::Framer;
+pub use context::{AnalysisContext, Transform};
+pub use window::WindowFunction;
+pub use constant_q::ConstantQ;
+pub use chroma::{chromagram, Key, KeyEstimator, Mode};
+pub use beat::{Beat, BeatTracker};
+pub use percussion::{Percussion, PercussionAnalyzer};
+pub use hpss::{split_bins, Hpss};
+pub use agc::{AgcSettings, AutomaticGainControl};
+pub use weighting::{LoudnessWeighting, WeightingCache};
+pub use equalizer::{EqPoint, GraphicEq};
+pub use noise::{NoiseProfile, NoiseReduction, NoiseSettings};
 
 type Buffer = Arc<Mutex<stream::InnerStream>>;
 
-/// Entry point for the raw input signal from the sound card
-pub fn tick(data: &[f32], buffer: Buffer) {
-    // Create a vector with the data of this frame and the last frame
-    // This is necessary to prevent data loss during the windowing for the fft
-    let mut input = vec![0.0; data.len()*2];
-    input[data.len()..].copy_from_slice(data);
-
-    if let Ok(mut buffer) = buffer.lock() {
-        // Initialize the frame, if it was not already
-        if buffer.last_frame.is_empty() { buffer.last_frame = vec![0.0; data.len()]; }
-
-        // Check if the input data of the frame is correct
-        if data.len() != buffer.last_frame.len() { return; }
-
-        // Save the last frame to the input
-        input[..data.len()].
-            copy_from_slice(&buffer.last_frame);
-        // Set the last frame new
-        buffer.last_frame.copy_from_slice(data);
-    }
-
-    // Apply a pre-emphasis filter on the input signal
-    let mut filtered = pre_emphasis(input.as_mut_slice());
-    // Apply the threshold filter
-    threshold_filter(filtered.as_mut_slice());
-
-    // Get the hanning window
-    let hann_window = get_hann_window(filtered.len()).expect("Wrong window length");
-    let mut windowed = array_product(&filtered, &hann_window).expect("Wrong window length");
-
-    // Process the fft
-    let magnitude = fft(&mut windowed);
-    // Calculate the power frames
-    let power_frames = magnitude.iter()
-        .map(|it| it.pow(2))
-        .collect::<Vec<f32>>();
+/// The part of the stream, which the analysis of a frame needs.
+/// It is copied under the lock, so the frames are analyzed without holding it
+struct FrameConfig {
+    settings: stream::Settings,
+    sample_rate: u32,
+    /// The amount of melbank bins, which the effect needs
+    n_bins: usize,
+    requires_hpss: bool,
+}
 
+/// Entry point for the raw input signal from the sound card
+pub fn tick(data: &[f32], buffer: &Buffer, sender: &mut Sender, context: &mut AnalysisContext) {
+    // All completed analysis frames for every signal
+    let mut frames: Vec<Vec<Vec<f32>>> = Vec::new();
+    let mut bass_frames: Vec<Vec<Vec<f32>>> = Vec::new();
+    let mut constant_q_frames: Vec<Vec<Vec<f32>>> = Vec::new();
+
+    let Ok(config) = buffer.lock().map(|mut buffer| {
+        // Split the interleaved input into all signals which should be analyzed
+        let per_channel = buffer.settings.channel_mode == ChannelMode::PerChannel
+            || buffer.effect.requires_channel_melbanks();
+        let signals = split_channels(data, buffer.channels as usize, per_channel);
+
+        // Create new framers, if the amount of signals or the frame settings changed.
+        // The bass framers use the same hop size, so they complete their frames together with the other framers
+        let window_size = buffer.settings.window_size;
+        let hop_size = buffer.settings.hop_size.min(window_size);
+        let bass_window_size = buffer.settings.bass_window_size.map(|it| it.max(window_size));
+        let n_bass_framers = if bass_window_size.is_some() { signals.len() } else { 0 };
+
+        let outdated = buffer.framers.len() != signals.len()
+            || buffer.bass_framers.len() != n_bass_framers
+            || buffer.framers.iter().any(|it| !it.has_config(window_size, hop_size))
+            || bass_window_size.is_some_and(|size| buffer.bass_framers.iter().any(|it| !it.has_config(size, hop_size)));
+        if outdated {
+            buffer.framers = signals.iter().map(|_| Framer::new(window_size, hop_size)).collect();
+            buffer.bass_framers = signals.iter()
+                .filter_map(|_| bass_window_size.map(|size| Framer::new(size, hop_size)))
+                .collect();
+        }
 
-    if let Ok(mut buffer) = buffer.try_lock() {
-        // Convert the fft frame to a melbank frame
-        let melbank = apply_mel_matrix(
-            &power_frames,
-            buffer.settings.min_frequency as f32,
-            buffer.settings.max_frequency as f32,
-            buffer.effect.amount_melbank_bins(buffer.settings.n_bins),
-            buffer.sample_rate
-        );
-
-        let data = AudioData {
-            melbank: melbank.as_slice(),
-            power_spectrum: power_frames.as_slice(),
-            raw_data: input.as_slice(),
-            settings: buffer.settings,
-            sample_rate: buffer.sample_rate,
-            color: buffer.color
+        // Every framer gets the same amount of samples, so all of them complete their frames at the same time
+        frames = signals.iter().zip(buffer.framers.iter_mut())
+            .map(|(signal, framer)| framer.push(signal))
+            .collect();
+        bass_frames = signals.iter().zip(buffer.bass_framers.iter_mut())
+            .map(|(signal, framer)| framer.push(signal))
+            .collect();
+
+        // The constant-Q transform gets its own history of every signal, which needs it.
+        // The history is as long as the kernel of the lowest bin, so no kernel is truncated
+        let n_constant_q = match buffer.settings.filterbank {
+            Filterbank::ConstantQ => signals.len(),
+            Filterbank::Triangular if buffer.effect.requires_constant_q() => 1,
+            Filterbank::Triangular => 0,
         };
+        let history = ConstantQ::history_length(buffer.settings.min_frequency as f32, buffer.settings.bins_per_octave, buffer.sample_rate)
+            .max(window_size);
+        if buffer.constant_q_framers.len() != n_constant_q || buffer.constant_q_framers.iter().any(|it| !it.has_config(history, hop_size)) {
+            buffer.constant_q_framers = (0..n_constant_q).map(|_| Framer::new(history, hop_size)).collect();
+        }
+        constant_q_frames = signals.iter().zip(buffer.constant_q_framers.iter_mut())
+            .map(|(signal, framer)| framer.push(signal))
+            .collect();
 
-        let out = buffer.effect.transpose_animation(data);
-
-        // Send the data
-        buffer.sender.send(out);
+        FrameConfig {
+            settings: buffer.settings.clone(),
+            sample_rate: buffer.sample_rate,
+            n_bins: buffer.effect.amount_melbank_bins(buffer.settings.n_bins),
+            requires_hpss: buffer.effect.requires_hpss(),
+        }
+    }) else { return };
+
+    // Process every completed frame in order
+    let amount = frames.first().map_or(0, Vec::len);
+    for i in 0..amount {
+        let inputs = frames.iter()
+            .map(|signal| signal[i].as_slice())
+            .collect::<Vec<&[f32]>>();
+        let bass_inputs = bass_frames.iter()
+            .map(|signal| signal[i].as_slice())
+            .collect::<Vec<&[f32]>>();
+        let constant_q_inputs = constant_q_frames.iter()
+            .map(|signal| signal[i].as_slice())
+            .collect::<Vec<&[f32]>>();
+        process_frame(&inputs, &bass_inputs, &constant_q_inputs, &config, buffer, sender, context);
     }
 }
 
-const PRE_EMPHASIS_CONST: f32 = 0.9;
-
-fn pre_emphasis(x: &[f32]) -> Vec<f32> {
-    let mut y = Vec::<f32>::new();
-
-    // y(t) = x(t) - a*x(t-1)
-    y.push(x[0]);
-    for i in 1..x.len() {
-        y.push(x[i] - PRE_EMPHASIS_CONST*x[i-1]);
+/// Analyze the frame of every signal and visualize them with the effect.
+/// The first input is always the mono downmix.
+/// If the multi-resolution analysis is enabled, the bass inputs contain a longer frame of every signal, otherwise they are empty.
+/// The constant-Q inputs contain the history of every signal, which needs the constant-Q transform.
+fn process_frame(
+    inputs: &[&[f32]],
+    bass_inputs: &[&[f32]],
+    constant_q_inputs: &[&[f32]],
+    config: &FrameConfig,
+    buffer: &Buffer,
+    sender: &mut Sender,
+    context: &mut AnalysisContext
+) {
+    let (settings, sample_rate, n_bins, requires_hpss) = (config.settings.clone(), config.sample_rate, config.n_bins, config.requires_hpss);
+    // The time between two frames in seconds, which is needed by every time based filter
+    let frame_interval = inputs[0].len().min(settings.hop_size) as f32 / sample_rate as f32;
+
+    // Get the gain of the mono signal and apply it on every signal, so the channels keep their balance
+    let gain = context.agc.update(inputs[0], &settings.agc, frame_interval);
+    let amplify = |input: &&[f32]| input.iter().map(|it| it * gain).collect::<Vec<f32>>();
+    let inputs = inputs.iter().map(amplify).collect::<Vec<Vec<f32>>>();
+    let bass_inputs = bass_inputs.iter().map(amplify).collect::<Vec<Vec<f32>>>();
+    let constant_q_inputs = constant_q_inputs.iter().map(amplify).collect::<Vec<Vec<f32>>>();
+
+    // Calculate the power spectrum of every signal
+    let mut spectra = inputs.iter()
+        .map(|input| power_spectrum(input, settings.fft_size, &settings, &mut context.transform))
+        .collect::<Vec<Vec<f32>>>();
+    let mut bass_spectra = inputs.iter().zip(bass_inputs.iter())
+        .map(|(input, bass_input)| bass_power_spectrum(input.len(), bass_input, &settings, &mut context.bass_transform))
+        .collect::<Vec<Vec<f32>>>();
+
+    // Learn the noise profile of every signal and remove the noise from its spectrum
+    let noise_settings = NoiseSettings {
+        sample_rate,
+        signals: inputs.len(),
+        window_size: inputs[0].len(),
+        fft_size: Transform::fft_size(inputs[0].len(), settings.fft_size),
+        bass_window_size: bass_inputs.first().map_or(0, Vec::len),
+        bass_fft_size: bass_inputs.first().map_or(0, |it| bass_fft_size(inputs[0].len(), it.len(), settings.fft_size)),
+        pre_emphasis: settings.pre_emphasis,
+        window_function: settings.window_function,
+    };
+    let Ok(noise_profile) = buffer.lock().map(|mut it| {
+        it.noise.update(&spectra, &bass_spectra, &noise_settings, gain, frame_interval);
+        it.noise.matching_profile(&noise_settings)
+    }) else { return };
+    if let Some(profile) = noise_profile {
+        spectra.iter_mut().enumerate().for_each(|(i, spectrum)| profile.subtract(i, spectrum, false, gain));
+        bass_spectra.iter_mut().enumerate().for_each(|(i, spectrum)| profile.subtract(i, spectrum, true, gain));
     }
 
-    y
+    // Convert the fft frames to melbank frames
+    let (min_frequency, max_frequency) = (settings.min_frequency as f32, settings.max_frequency as f32);
+    // The constant-Q transform of every signal, which needs it
+    let mut constant_q = constant_q_inputs.iter()
+        .map(|input| context.constant_q.apply(
+            input,
+            min_frequency,
+            n_bins,
+            settings.bins_per_octave,
+            sample_rate
+        ))
+        .collect::<Vec<Vec<f32>>>();
+    // Apply the graphic equalizer with the center frequency of every bin
+    constant_q.iter_mut().for_each(|it| settings.equalizer.apply(it, context.constant_q.center_frequencies()));
+
+    context.melbank.scale = settings.frequency_scale;
+    context.melbank.normalization = settings.filter_normalization;
+    // With the constant-Q filterbank, the effects get the constant-Q transform as melbank
+    let melbanks = if settings.filterbank == Filterbank::ConstantQ {
+        let mut melbanks = constant_q.clone();
+        melbanks.iter_mut().for_each(|it| context.constant_q_weighting.apply(it, context.constant_q.center_frequencies(), settings.loudness_weighting, sample_rate));
+        melbanks
+    } else {
+        // The loudness weighting only changes the melbanks, the analysis stages use the unweighted spectra
+        let weighting = settings.loudness_weighting;
+        let weighted = weighted_spectra(&spectra, context.transform.frequencies(sample_rate), weighting, sample_rate, &mut context.weighting);
+        let bass_weighted = weighted_spectra(&bass_spectra, context.bass_transform.frequencies(sample_rate), weighting, sample_rate, &mut context.bass_weighting);
+        let mut melbanks = weighted.iter().enumerate()
+            .map(|(i, spectrum)| match bass_weighted.get(i) {
+                Some(bass_spectrum) => context.melbank.apply_multi_resolution(
+                    spectrum,
+                    bass_spectrum,
+                    (inputs[i].len(), bass_inputs[i].len()),
+                    settings.crossover_frequency as f32,
+                    min_frequency,
+                    max_frequency,
+                    n_bins,
+                    sample_rate
+                ),
+                None => context.melbank.apply(spectrum, min_frequency, max_frequency, n_bins, sample_rate),
+            })
+            .collect::<Vec<Vec<f32>>>();
+        melbanks.iter_mut().for_each(|it| settings.equalizer.apply(it, context.melbank.center_frequencies()));
+        melbanks
+    };
+
+    // The chromagram uses the finer resolution of the bass spectrum, if available
+    let chroma = match bass_spectra.first() {
+        Some(bass_spectrum) => chromagram(bass_spectrum, context.bass_transform.frequencies(sample_rate)),
+        None => chromagram(&spectra[0], context.transform.frequencies(sample_rate)),
+    };
+    let key = context.key.update(&chroma, frame_interval);
+
+    let onset = context.onset.update(&spectra[0], frame_interval);
+    let beat = context.beat.update(onset, frame_interval);
+    let percussion = context.percussion.update(&spectra[0], sample_rate, frame_interval);
+
+    // Separate the harmonic and percussive parts of the mono signal, if the effect needs them
+    let (separated, harmonic_constant_q) = if requires_hpss {
+        let (harmonic, percussive) = context.hpss.separate(&spectra[0], context.transform.frequencies(sample_rate), frame_interval);
+        let bass = bass_spectra.first().map(|it| context.bass_hpss.separate(it, context.bass_transform.frequencies(sample_rate), frame_interval));
+
+        // The constant-Q transform is calculated from the samples, so the masks of the separation are applied on its bins
+        let constant_q_frequencies = context.constant_q.center_frequencies();
+        let split_constant_q = constant_q.first().map(|bins| match bass.as_ref() {
+            Some((harmonic, percussive)) => split_bins(bins, constant_q_frequencies, harmonic, percussive, context.bass_transform.frequencies(sample_rate)),
+            None => split_bins(bins, constant_q_frequencies, &harmonic, &percussive, context.transform.frequencies(sample_rate)),
+        });
+
+        let separated = match (settings.filterbank, split_constant_q.clone()) {
+            // The bins of the constant-Q transform are already equalized, but not weighted yet
+            (Filterbank::ConstantQ, Some(mut separated)) => {
+                separated.iter_mut().for_each(|it| context.constant_q_weighting.apply(it, constant_q_frequencies, settings.loudness_weighting, sample_rate));
+                separated
+            }
+            // The separated melbanks use the same resolutions as the melbank
+            _ => {
+                context.hpss.melbank.scale = settings.frequency_scale;
+                context.hpss.melbank.normalization = settings.filter_normalization;
+                let weighting = settings.loudness_weighting;
+                let weighted = weighted_spectra(&[harmonic, percussive], context.transform.frequencies(sample_rate), weighting, sample_rate, &mut context.weighting);
+                let bass_weighted = bass.map(|(harmonic, percussive)| weighted_spectra(&[harmonic, percussive], context.bass_transform.frequencies(sample_rate), weighting, sample_rate, &mut context.bass_weighting));
+                let mut separated = [0, 1].map(|i| match bass_weighted.as_ref() {
+                    Some(bass_spectra) => context.hpss.melbank.apply_multi_resolution(
+                        &weighted[i],
+                        &bass_spectra[i],
+                        (inputs[0].len(), bass_inputs[0].len()),
+                        settings.crossover_frequency as f32,
+                        min_frequency,
+                        max_frequency,
+                        n_bins,
+                        sample_rate
+                    ),
+                    None => context.hpss.melbank.apply(&weighted[i], min_frequency, max_frequency, n_bins, sample_rate),
+                });
+                separated.iter_mut().for_each(|it| settings.equalizer.apply(it, context.hpss.melbank.center_frequencies()));
+                separated
+            }
+        };
+
+        let harmonic_constant_q = split_constant_q.map(|[harmonic, _]| harmonic).unwrap_or_default();
+        (separated, harmonic_constant_q)
+    } else {
+        ([Vec::new(), Vec::new()], Vec::new())
+    };
+
+    let mut data = AudioData {
+        melbank: melbanks[0].as_slice(),
+        channel_melbanks: &melbanks[1..],
+        constant_q: if requires_hpss { harmonic_constant_q.as_slice() } else { constant_q.first().map_or(&[], Vec::as_slice) },
+        chroma,
+        key,
+        onset,
+        beat,
+        percussion,
+        harmonic_melbank: separated[0].as_slice(),
+        percussive_melbank: separated[1].as_slice(),
+        power_spectrum: spectra[0].as_slice(),
+        frequencies: context.transform.frequencies(sample_rate),
+        raw_data: &inputs[0],
+        settings,
+        sample_rate,
+        frame_interval,
+        color: [0, 0, 0],
+    };
+
+    // Only the effect runs under the lock again, with the latest color
+    let Ok(out) = buffer.lock().map(|mut it| {
+        data.color = it.color;
+        it.effect.transpose_animation(data)
+    }) else { return };
+
+    // Send the data
+    sender.send(out);
 }
 
-const THRESHOLD: f32 = 0.0002;
-fn threshold_filter(x: &mut [f32]) {
-    if let Some(max) = x.iter().max_by(|x, y| x.partial_cmp(y).unwrap()) {
-        if *max <= THRESHOLD {
-            x.iter_mut().for_each(|v| *v = 0.0);
-        }
+/// Split the interleaved samples into the signals which should be analyzed.
+/// The first signal is always the mono downmix of all channels.
+/// If per_channel is set, every channel of a multichannel input follows as its own signal.
+pub(crate) fn split_channels(data: &[f32], channels: usize, per_channel: bool) -> Vec<Vec<f32>> {
+    let channels = channels.max(1);
+
+    // Average all channels of a frame
+    let downmix = data.chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect::<Vec<f32>>();
 
+    let mut signals = vec![downmix];
+    if per_channel && channels > 1 {
+        for channel in 0..channels {
+            signals.push(data.iter().skip(channel).step_by(channels).copied().collect());
+        }
     }
 
+    signals
 }
 
-fn fft(filtered: &mut [f32]) -> Vec<f32> {
-    // Prepare for the fft
-    let mut planner = RealFftPlanner::<f32>::new();
-    let fft = planner.plan_fft_forward(filtered.len());
-
-    // Run the fft and get the spectrum
-    let mut spectrum = fft.make_output_vec();
-    fft.process(filtered, &mut spectrum).expect("fft process failed. Is the buffer length correct?");
-    let amplitude = &spectrum[0..(spectrum.len()/2)];
+/// Calculate the power spectrum of the input signal
+pub(crate) fn power_spectrum(input: &[f32], fft_size: usize, settings: &stream::Settings, transform: &mut Transform) -> Vec<f32> {
+    // Apply a pre-emphasis filter on the input signal
+    let filtered = pre_emphasis(input, settings.pre_emphasis);
 
-    // Calculate the magnitude of the fft spectrum
-    amplitude.iter().map(|it| {
-        it.norm()
-    }).collect::<Vec<f32>>()
+    // Apply the window and process the zero padded fft
+    transform.power_spectrum(&filtered, fft_size, settings.window_function)
 }
 
-pub fn apply_mel_matrix(fft: &[f32], min_freq: f32, max_freq: f32, bins: usize,  sample_rate: u32) -> Vec<f32> {
-    let matrix = melbank::compute_mel_matrix(bins, fft.len(), min_freq, max_freq, sample_rate as u16);
+/// Weight a copy of every power spectrum with the perceived loudness
+fn weighted_spectra(spectra: &[Vec<f32>], frequencies: &[f32], weighting: LoudnessWeighting, sample_rate: u32, cache: &mut WeightingCache) -> Vec<Vec<f32>> {
+    spectra.iter()
+        .map(|spectrum| {
+            let mut spectrum = spectrum.clone();
+            cache.apply(&mut spectrum, frequencies, weighting, sample_rate);
+            spectrum
+        })
+        .collect()
+}
 
-    // Create a vector with the length of the matrix size
-    let mut calculated = vec![0.0f32; matrix.len()];
+/// Calculate the power spectrum of the long bass frame.
+/// The fft is zero padded by the same factor as the short frame.
+/// Its level differs from the short frame, so the melbank has to match both levels.
+pub(crate) fn bass_power_spectrum(frame_length: usize, input: &[f32], settings: &stream::Settings, transform: &mut Transform) -> Vec<f32> {
+    power_spectrum(input, bass_fft_size(frame_length, input.len(), settings.fft_size), settings, transform)
+}
 
-    // go through every mel bin
-    for (i, bin) in matrix.iter().enumerate() {
+/// The fft size of the long bass frame, which is zero padded by the same factor as the short frame
+fn bass_fft_size(frame_length: usize, bass_frame_length: usize, fft_size: usize) -> usize {
+    let fft_size = Transform::fft_size(frame_length, fft_size);
+    Transform::fft_size(bass_frame_length, fft_size * bass_frame_length / frame_length.max(1))
+}
 
-        // and multiply the actual data with the pre-constructed mel bin
-        calculated[i] = bin.iter().zip(fft.iter())
-            .map(|(a, b)| *a * *b)
-            .sum::<f32>();
+/// Boost the high frequencies with a first order filter. A coefficient of 0 leaves the signal unchanged
+fn pre_emphasis(x: &[f32], coefficient: f32) -> Vec<f32> {
+    let mut y = Vec::<f32>::with_capacity(x.len());
 
+    // y(t) = x(t) - a*x(t-1)
+    y.extend(x.first());
+    for i in 1..x.len() {
+        y.push(x[i] - coefficient*x[i-1]);
     }
 
-    calculated
+    y
 }
@@ -0,0 +1,83 @@
+use super::smoothing::ExponentialFilter;
+
+/// The level in dBFS, which the gain control amplifies the input to
+const TARGET_LEVEL: f32 = -20.0;
+/// The attack and release time of the level envelope in milliseconds.
+/// A loud input reduces the gain fast, while the gain rises slowly in quiet parts
+const ENVELOPE_SMOOTHING: (f32, f32) = (10.0, 2000.0);
+
+/// The settings of the automatic gain control and its noise gate
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AgcSettings {
+    /// The rms level in dBFS, below which the input is treated as noise
+    pub noise_floor: f32,
+    /// The gate opens only if the level is this many dB above the noise floor,
+    /// but closes when it falls below the noise floor
+    pub hysteresis: f32,
+    /// The time in milliseconds, which the gate stays open after the level fell below the noise floor
+    pub hold_time: f32,
+    /// The largest amplification in dB
+    pub max_gain: f32,
+}
+
+impl Default for AgcSettings {
+    fn default() -> Self {
+        AgcSettings {
+            noise_floor: -70.0,
+            hysteresis: 6.0,
+            hold_time: 200.0,
+            max_gain: 30.0,
+        }
+    }
+}
+
+/// Convert the level in dB to a linear factor of the amplitude
+fn db_to_amplitude(db: f32) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
+
+/// Automatic gain control of the input with a noise gate.
+/// The input is amplified to the target level, but never more than the max gain,
+/// and muted while the gate is closed, so noise doesn't light up the effects.
+pub struct AutomaticGainControl {
+    envelope: ExponentialFilter<f32>,
+    open: bool,
+    /// The time in milliseconds since the level fell below the noise floor
+    hold: f32,
+}
+
+impl AutomaticGainControl {
+
+    pub fn new() -> AutomaticGainControl {
+        AutomaticGainControl {
+            envelope: ExponentialFilter::new(db_to_amplitude(TARGET_LEVEL), ENVELOPE_SMOOTHING.0, ENVELOPE_SMOOTHING.1),
+            open: false,
+            hold: 0.0,
+        }
+    }
+
+    /// Update the gate and the envelope with the frame and get the gain for it.
+    /// The frame interval is the time between two frames in seconds.
+    /// Returns 0, if the gate is closed.
+    pub fn update(&mut self, frame: &[f32], settings: &AgcSettings, frame_interval: f32) -> f32 {
+        let rms = (frame.iter().map(|it| it * it).sum::<f32>() / frame.len().max(1) as f32).sqrt();
+        let level = 20.0 * rms.max(f32::MIN_POSITIVE).log10();
+
+        // Open the gate above the upper threshold and close it after the hold time below the noise floor
+        if level >= settings.noise_floor + settings.hysteresis {
+            self.open = true;
+            self.hold = 0.0;
+        } else if level >= settings.noise_floor {
+            self.hold = 0.0;
+        } else if self.open {
+            self.hold += frame_interval * 1000.0;
+            self.open = self.hold <= settings.hold_time;
+        }
+
+        if !self.open { return 0.0; }
+
+        // The envelope only follows the signal while the gate is open, so the gain doesn't rise to the max during silence
+        let envelope = self.envelope.update(rms, frame_interval);
+        (db_to_amplitude(TARGET_LEVEL) / envelope.max(f32::MIN_POSITIVE)).min(db_to_amplitude(settings.max_gain))
+    }
+}
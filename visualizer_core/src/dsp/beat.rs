@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use super::detection::Onset;
+
+/// The duration of the onset envelope, which is used for the tempo estimation in seconds
+const ENVELOPE_DURATION: f32 = 8.0;
+/// The tempo is only estimated, if the envelope is at least this long
+const MIN_ENVELOPE_DURATION: f32 = 2.0;
+/// The time between two tempo estimations in seconds
+const TEMPO_UPDATE_INTERVAL: f32 = 0.5;
+/// The range of detectable tempos in beats per minute
+const TEMPO_RANGE: (f32, f32) = (60.0, 200.0);
+/// Tempos close to this tempo are preferred, to decide between half and double tempo
+const PREFERRED_TEMPO: f32 = 120.0;
+/// The width of the tempo preference in octaves
+const TEMPO_PREFERENCE_WIDTH: f32 = 1.0;
+/// The part of the phase error, which is corrected with every tempo estimation
+const PHASE_CORRECTION: f32 = 0.5;
+/// The part of a beat period around every beat, where onsets are counted as accent of the beat
+const ACCENT_WINDOW: f32 = 0.15;
+/// The factor, which the accents decay with every bar
+const ACCENT_DECAY: f32 = 0.9;
+const BEATS_PER_BAR: usize = 4;
+
+/// The state of the beat clock in a single frame
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Beat {
+    /// The estimated tempo in beats per minute. 0, if no tempo was estimated yet
+    pub bpm: f32,
+    /// The progress to the next beat from 0 to 1
+    pub phase: f32,
+    /// True, if a beat happened in this frame
+    pub beat: bool,
+    /// True, if the beat is the first beat of a bar
+    pub downbeat: bool,
+}
+
+/// Estimates the tempo with the autocorrelation of the onset envelope and runs a beat clock with it.
+/// The phase of the clock is aligned to the onsets with a comb over the envelope.
+pub struct BeatTracker {
+    envelope: VecDeque<f32>,
+    since_update: f32,
+    /// The beat period in frames. 0, if no tempo was estimated yet
+    period: f32,
+    bpm: f32,
+    phase: f32,
+    /// The position of the last beat in the bar
+    bar_position: usize,
+    /// The accumulated onset strength at every position of the bar
+    accents: [f32; BEATS_PER_BAR],
+    accent_pending: bool,
+}
+
+impl BeatTracker {
+
+    pub fn new() -> BeatTracker {
+        BeatTracker {
+            envelope: VecDeque::new(),
+            since_update: 0.0,
+            period: 0.0,
+            bpm: 0.0,
+            phase: 0.0,
+            bar_position: 0,
+            accents: [0.0; BEATS_PER_BAR],
+            accent_pending: false,
+        }
+    }
+
+    /// Add the onset of the frame to the envelope and advance the beat clock.
+    /// The frame interval is the time between two frames in seconds.
+    pub fn update(&mut self, onset: Onset, frame_interval: f32) -> Beat {
+        let frame_interval = frame_interval.max(f32::EPSILON);
+
+        let length = (ENVELOPE_DURATION / frame_interval).ceil() as usize;
+        self.envelope.push_back(onset.strength);
+        while self.envelope.len() > length {
+            self.envelope.pop_front();
+        }
+
+        self.since_update += frame_interval;
+        if self.since_update >= TEMPO_UPDATE_INTERVAL && self.envelope.len() as f32 * frame_interval >= MIN_ENVELOPE_DURATION {
+            self.since_update = 0.0;
+            self.estimate_tempo(frame_interval);
+        }
+
+        if self.period <= 0.0 {
+            return Beat::default();
+        }
+
+        // Advance the beat clock
+        self.phase += 1.0 / self.period;
+        let mut beat = Beat { bpm: self.bpm, ..Beat::default() };
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.bar_position = (self.bar_position + 1) % BEATS_PER_BAR;
+            self.accent_pending = true;
+
+            // The position with the strongest accents is the first beat of the bar
+            let downbeat = (0..BEATS_PER_BAR)
+                .max_by(|a, b| self.accents[*a].total_cmp(&self.accents[*b]))
+                .unwrap_or(0);
+            beat.beat = true;
+            beat.downbeat = self.bar_position == downbeat;
+        }
+
+        // Once the accent window after the beat passed, the strongest onset around the beat is its accent
+        if self.accent_pending && self.phase >= ACCENT_WINDOW {
+            self.accent_pending = false;
+            let frames = (2.0 * ACCENT_WINDOW * self.period).ceil() as usize;
+            let accent = self.envelope.iter().rev().take(frames).cloned().fold(0.0, f32::max);
+            self.accents[self.bar_position] = self.accents[self.bar_position] * ACCENT_DECAY + accent;
+        }
+
+        beat.phase = self.phase;
+        beat
+    }
+
+    /// Estimate the tempo with the autocorrelation of the envelope and correct the phase of the beat clock
+    fn estimate_tempo(&mut self, frame_interval: f32) {
+        let mean = self.envelope.iter().sum::<f32>() / self.envelope.len() as f32;
+        let envelope = self.envelope.iter().map(|it| it - mean).collect::<Vec<f32>>();
+
+        // The lags of the tempo range in frames
+        let min_lag = (60.0 / (TEMPO_RANGE.1 * frame_interval)).floor().max(1.0) as usize;
+        let max_lag = ((60.0 / (TEMPO_RANGE.0 * frame_interval)).ceil() as usize).min(envelope.len() / 2);
+        if min_lag + 2 > max_lag { return; }
+
+        let autocorrelation = (min_lag - 1..=max_lag + 1)
+            .map(|lag| {
+                let sum = envelope.iter().zip(envelope.iter().skip(lag)).map(|(a, b)| a * b).sum::<f32>();
+                sum / (envelope.len() - lag) as f32
+            })
+            .collect::<Vec<f32>>();
+
+        // Weight every tempo with its distance to the preferred tempo
+        let score = |i: usize| {
+            let bpm = 60.0 / ((min_lag - 1 + i) as f32 * frame_interval);
+            let octaves = (bpm / PREFERRED_TEMPO).log2() / TEMPO_PREFERENCE_WIDTH;
+            autocorrelation[i] * (-0.5 * octaves * octaves).exp()
+        };
+        let Some(best) = (1..autocorrelation.len() - 1)
+            .max_by(|a, b| score(*a).total_cmp(&score(*b))) else { return };
+        if autocorrelation[best] <= 0.0 { return; }
+
+        // Parabolic interpolation between the neighbour lags
+        let (left, center, right) = (autocorrelation[best - 1], autocorrelation[best], autocorrelation[best + 1]);
+        let divisor = left - 2.0 * center + right;
+        let offset = if divisor.abs() > f32::EPSILON { (0.5 * (left - right) / divisor).clamp(-0.5, 0.5) } else { 0.0 };
+
+        self.period = (min_lag - 1 + best) as f32 + offset;
+        self.bpm = 60.0 / (self.period * frame_interval);
+
+        self.align_phase();
+    }
+
+    /// Find the offset of the last beat with a comb over the envelope and move the phase towards it
+    fn align_phase(&mut self) {
+        let period = self.period.round().max(1.0) as usize;
+        let score = |offset: usize| {
+            self.envelope.iter().rev().skip(offset).step_by(period).sum::<f32>()
+        };
+        let Some(offset) = (0..period).max_by(|a, b| score(*a).total_cmp(&score(*b))) else { return };
+
+        // The last beat was offset frames ago
+        let target = offset as f32 / self.period;
+        let error = (self.phase - target + 0.5).rem_euclid(1.0) - 0.5;
+
+        // The phase never moves back before the last beat, so no beat is reported twice
+        self.phase = (self.phase - PHASE_CORRECTION * error).max(0.0);
+    }
+}
@@ -0,0 +1,101 @@
+use super::smoothing::ExponentialFilter;
+
+/// The frequency range, which is used for the chromagram
+const CHROMA_RANGE: (f32, f32) = (55.0, 5000.0);
+
+/// The time constant of the chromagram for the key estimation in milliseconds, so the key only changes after a few seconds
+const KEY_SMOOTHING: f32 = 1000.0;
+
+/// Key profiles of Krumhansl and Kessler, beginning with the tonic
+const MAJOR_PROFILE: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+/// Sum the power of every frequency into its pitch class.
+/// The first bin is C and the last bin is B. The chromagram is normalized, so its largest bin is 1.
+pub fn chromagram(spectrum: &[f32], frequencies: &[f32]) -> [f32; 12] {
+    let mut chroma = [0.0; 12];
+
+    for (power, frequency) in spectrum.iter().zip(frequencies) {
+        if !(CHROMA_RANGE.0..=CHROMA_RANGE.1).contains(frequency) { continue; }
+
+        // The semitone relative to A4, moved to begin with C
+        let semitone = (12.0 * (frequency / 440.0).log2()).round() as i32 + 9;
+        chroma[semitone.rem_euclid(12) as usize] += power;
+    }
+
+    let max = chroma.iter().cloned().fold(0.0, f32::max);
+    if max > 0.0 {
+        chroma.iter_mut().for_each(|it| *it /= max);
+    }
+
+    chroma
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A musical key
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Key {
+    /// The pitch class of the tonic. 0 is C and 11 is B
+    pub tonic: usize,
+    pub mode: Mode,
+    /// The correlation of the chromagram with the key profile from 0 to 1
+    pub confidence: f32,
+}
+
+/// Estimates the key from the chromagram, which is smoothed over a few seconds
+pub struct KeyEstimator {
+    chroma_filter: ExponentialFilter<Vec<f32>>,
+}
+
+impl KeyEstimator {
+
+    pub fn new() -> KeyEstimator {
+        KeyEstimator {
+            chroma_filter: ExponentialFilter::new(vec![0.0; 12], KEY_SMOOTHING, KEY_SMOOTHING),
+        }
+    }
+
+    /// Update the smoothed chromagram and get the key with the highest correlation.
+    /// The frame interval is the time between two frames in seconds.
+    /// Returns None, if there was no tonal content yet
+    pub fn update(&mut self, chroma: &[f32; 12], frame_interval: f32) -> Option<Key> {
+        let mut smoothed = chroma.to_vec();
+        self.chroma_filter.update(&mut smoothed, frame_interval);
+
+        // Compare the chromagram with every major and minor key
+        let mut best: Option<Key> = None;
+        for tonic in 0..12 {
+            for (mode, profile) in [(Mode::Major, &MAJOR_PROFILE), (Mode::Minor, &MINOR_PROFILE)] {
+                let rotated = (0..12).map(|i| profile[(i + 12 - tonic) % 12]).collect::<Vec<f32>>();
+                let Some(correlation) = correlation(&smoothed, &rotated) else { continue };
+
+                if best.is_none_or(|it| correlation > it.confidence) {
+                    best = Some(Key { tonic, mode, confidence: correlation });
+                }
+            }
+        }
+
+        best.map(|key| Key { confidence: key.confidence.max(0.0), ..key })
+    }
+}
+
+/// Pearson correlation of two signals. None, if one of them is constant
+fn correlation(a: &[f32], b: &[f32]) -> Option<f32> {
+    let mean = |x: &[f32]| x.iter().sum::<f32>() / x.len() as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+
+    let norm = (variance_a * variance_b).sqrt();
+    if norm > f32::EPSILON { Some(covariance / norm) } else { None }
+}
@@ -0,0 +1,122 @@
+use std::f32::consts::PI;
+
+/// The reference frequency of the note grid (A4)
+const REFERENCE_FREQUENCY: f32 = 440.0;
+
+/// A single kernel of the constant-Q transform:
+/// A hann windowed complex sinusoid with the center frequency of the bin.
+/// The length of the kernel is inversely proportional to its frequency, so every bin has the same quality factor.
+struct Kernel {
+    cos: Vec<f32>,
+    sin: Vec<f32>,
+}
+
+impl Kernel {
+
+    fn new(frequency: f32, length: usize, sample_rate: u32) -> Kernel {
+        let window = (0..length)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / length.max(2) as f32).cos())
+            .collect::<Vec<f32>>();
+        // Divide by the sum of the window, so a sinusoid has the same magnitude in every bin
+        let sum = window.iter().sum::<f32>().max(f32::EPSILON);
+
+        let phase = |n: usize| 2.0 * PI * frequency * n as f32 / sample_rate as f32;
+        Kernel {
+            cos: window.iter().enumerate().map(|(n, w)| w * phase(n).cos() / sum).collect(),
+            sin: window.iter().enumerate().map(|(n, w)| w * phase(n).sin() / sum).collect(),
+        }
+    }
+
+    /// Calculate the power of the bin from the latest samples of the frame
+    fn apply(&self, frame: &[f32]) -> f32 {
+        let samples = &frame[frame.len().saturating_sub(self.cos.len())..];
+
+        let (mut re, mut im) = (0.0, 0.0);
+        for ((x, c), s) in samples.iter().zip(self.cos.iter()).zip(self.sin.iter()) {
+            re += x * c;
+            im += x * s;
+        }
+
+        re * re + im * im
+    }
+}
+
+/// The parameters, the kernels were computed with
+#[derive(PartialEq)]
+struct ConstantQKey {
+    n_bins: usize,
+    bins_per_octave: usize,
+    min_freq: f32,
+    frame_length: usize,
+    sample_rate: u32,
+}
+
+/// Constant-Q transform with note aligned bins.
+/// The kernels are only computed again if their parameters change.
+pub struct ConstantQ {
+    key: Option<ConstantQKey>,
+    kernels: Vec<Kernel>,
+    /// The center frequency of every bin in Hz
+    frequencies: Vec<f32>,
+}
+
+impl ConstantQ {
+
+    pub fn new() -> ConstantQ {
+        ConstantQ {
+            key: None,
+            kernels: Vec::new(),
+            frequencies: Vec::new(),
+        }
+    }
+
+    /// The center frequency of every bin of the last transform in Hz
+    pub fn center_frequencies(&self) -> &[f32] {
+        &self.frequencies
+    }
+
+    /// Get the center frequency of every bin.
+    /// The first bin is the note closest to the min frequency, all further bins follow with bins_per_octave bins per octave.
+    pub fn frequencies(n_bins: usize, bins_per_octave: usize, min_freq: f32) -> Vec<f32> {
+        let bins_per_octave = bins_per_octave.max(1) as f32;
+        let first = (bins_per_octave * (min_freq.max(1.0) / REFERENCE_FREQUENCY).log2()).round();
+
+        (0..n_bins)
+            .map(|k| REFERENCE_FREQUENCY * 2.0_f32.powf((first + k as f32) / bins_per_octave))
+            .collect()
+    }
+
+    /// The length of the kernel of the bin with the frequency. Bins above the nyquist frequency have no kernel
+    fn kernel_length(frequency: f32, bins_per_octave: usize, sample_rate: u32) -> usize {
+        // The quality factor is the ratio of the center frequency to the bandwidth of a bin
+        let q = 1.0 / (2.0_f32.powf(1.0 / bins_per_octave.max(1) as f32) - 1.0);
+
+        if frequency < sample_rate as f32 / 2.0 { (q * sample_rate as f32 / frequency).ceil() as usize } else { 0 }
+    }
+
+    /// The amount of samples, which the kernel of the lowest bin needs.
+    /// A shorter frame truncates the kernels of the low bins, so they can't resolve their notes anymore
+    pub fn history_length(min_freq: f32, bins_per_octave: usize, sample_rate: u32) -> usize {
+        Self::frequencies(1, bins_per_octave, min_freq).first()
+            .map_or(0, |f| Self::kernel_length(*f, bins_per_octave, sample_rate))
+    }
+
+    /// Calculate the power of every note bin of the frame.
+    /// The kernels of the low notes are limited to the frame length, so the frame should be at least as long as the [history length](ConstantQ::history_length).
+    /// Bins above the nyquist frequency are always zero.
+    pub fn apply(&mut self, frame: &[f32], min_freq: f32, bins: usize, bins_per_octave: usize, sample_rate: u32) -> Vec<f32> {
+        let key = ConstantQKey { n_bins: bins, bins_per_octave, min_freq, frame_length: frame.len(), sample_rate };
+
+        if self.key.as_ref() != Some(&key) {
+            self.frequencies = Self::frequencies(bins, bins_per_octave, min_freq);
+            self.kernels = self.frequencies.iter()
+                .map(|f| Kernel::new(*f, Self::kernel_length(*f, bins_per_octave, sample_rate).min(frame.len()), sample_rate))
+                .collect();
+            self.key = Some(key);
+        }
+
+        self.kernels.iter()
+            .map(|kernel| kernel.apply(frame))
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use super::{AutomaticGainControl, BeatTracker, ConstantQ, Hpss, KeyEstimator, OnsetDetector, PercussionAnalyzer, WeightingCache};
+use super::melbank::{fft_frequencies, MelbankCache};
+use super::window::WindowFunction;
+
+/// All resources of the analysis, which are expensive to create and only change with the settings.
+/// The long transform is used for the bass frames of the multi-resolution analysis.
+pub struct AnalysisContext {
+    pub transform: Transform,
+    pub bass_transform: Transform,
+    pub weighting: WeightingCache,
+    pub bass_weighting: WeightingCache,
+    pub constant_q_weighting: WeightingCache,
+    pub melbank: MelbankCache,
+    pub constant_q: ConstantQ,
+    pub key: KeyEstimator,
+    pub onset: OnsetDetector,
+    pub beat: BeatTracker,
+    pub percussion: PercussionAnalyzer,
+    pub hpss: Hpss,
+    pub bass_hpss: Hpss,
+    pub agc: AutomaticGainControl,
+}
+
+impl AnalysisContext {
+
+    pub fn new() -> AnalysisContext {
+        AnalysisContext {
+            transform: Transform::new(),
+            bass_transform: Transform::new(),
+            weighting: WeightingCache::new(),
+            bass_weighting: WeightingCache::new(),
+            constant_q_weighting: WeightingCache::new(),
+            melbank: MelbankCache::new(),
+            constant_q: ConstantQ::new(),
+            key: KeyEstimator::new(),
+            onset: OnsetDetector::new(),
+            beat: BeatTracker::new(),
+            percussion: PercussionAnalyzer::new(),
+            hpss: Hpss::new(),
+            bass_hpss: Hpss::new(),
+            agc: AutomaticGainControl::new(),
+        }
+    }
+}
+
+/// Windowed and zero padded fft of a frame.
+/// The fft plan is only created again if the fft size changes, the window if the frame size or the window function changes.
+pub struct Transform {
+    planner: RealFftPlanner<f32>,
+    fft: Option<Arc<dyn RealToComplex<f32>>>,
+    window: Vec<f32>,
+    window_function: WindowFunction,
+    /// The windowed frame, zero padded to the fft size
+    input: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// The frequency of every bin of the power spectrum
+    frequencies: Vec<f32>,
+    sample_rate: u32,
+}
+
+impl Transform {
+
+    pub fn new() -> Transform {
+        Transform {
+            planner: RealFftPlanner::new(),
+            fft: None,
+            window: Vec::new(),
+            window_function: WindowFunction::default(),
+            input: Vec::new(),
+            spectrum: Vec::new(),
+            scratch: Vec::new(),
+            frequencies: Vec::new(),
+            sample_rate: 0,
+        }
+    }
+
+    /// Get the fft size, which is used for the frame length and the requested fft size.
+    /// The fft size is always a power of two and never shorter than the frame.
+    pub fn fft_size(frame_length: usize, fft_size: usize) -> usize {
+        fft_size.max(frame_length).max(2).next_power_of_two()
+    }
+
+    /// Plan the fft and create the window, if one of them changed
+    fn prepare(&mut self, frame_length: usize, fft_size: usize, window_function: WindowFunction) -> Arc<dyn RealToComplex<f32>> {
+        if self.window.len() != frame_length || self.window_function != window_function {
+            self.window = window_function.create(frame_length);
+            self.window_function = window_function;
+        }
+
+        if let Some(fft) = self.fft.as_ref()
+            && fft.len() == fft_size {
+            return fft.clone();
+        }
+
+        let fft = self.planner.plan_fft_forward(fft_size);
+        self.spectrum = fft.make_output_vec();
+        self.scratch = fft.make_scratch_vec();
+        self.fft = Some(fft.clone());
+
+        fft
+    }
+
+    /// Apply the window on the frame and calculate its power spectrum.
+    /// The frame is zero padded to the fft size (see [Transform::fft_size]),
+    /// so the spectrum has fft_size/2 + 1 bins from 0 Hz to the nyquist frequency.
+    pub fn power_spectrum(&mut self, frame: &[f32], fft_size: usize, window_function: WindowFunction) -> Vec<f32> {
+        let fft_size = Self::fft_size(frame.len(), fft_size);
+        let fft = self.prepare(frame.len(), fft_size, window_function);
+
+        // Apply the window and fill the rest of the input with zeros
+        self.input.clear();
+        self.input.extend(frame.iter().zip(self.window.iter()).map(|(value, weight)| *value * *weight));
+        self.input.resize(fft_size, 0.0);
+
+        // Run the fft and get the spectrum
+        fft.process_with_scratch(&mut self.input, &mut self.spectrum, &mut self.scratch)
+            .expect("fft process failed. Is the buffer length correct?");
+
+        // Calculate the power of every frequency
+        self.spectrum.iter()
+            .map(|it| it.norm_sqr())
+            .collect::<Vec<f32>>()
+    }
+
+    /// Get the frequency of every bin of the last power spectrum in Hz
+    pub fn frequencies(&mut self, sample_rate: u32) -> &[f32] {
+        if self.frequencies.len() != self.spectrum.len() || self.sample_rate != sample_rate {
+            self.frequencies = fft_frequencies(self.spectrum.len(), sample_rate);
+            self.sample_rate = sample_rate;
+        }
+
+        &self.frequencies
+    }
+}
@@ -1,4 +1,6 @@
-use super::smoothing::ExponentialFilter;
+use std::collections::VecDeque;
+
+use super::smoothing::{median, ExponentialFilter};
 
 
 pub struct PeakDetector {
@@ -14,16 +16,17 @@ impl PeakDetector {
 
     /// Creates a new peak detector object
     ///
-    /// accuracy: Defines the type of peaks to detect. From 0.1 to 0.9
-    /// A higher value means a faster adjustment to the original signal and a better detection for short and high peaks. (Heavily used in Hip-Hop, Pop).
-    /// A lower value means a less adjustment to the original signal and a better detection for long peaks. (Used in Rock or Punk etc.)
+    /// accuracy: Defines the type of peaks to detect. The release time of the average signal in milliseconds, mostly from 20 to 200
+    /// A shorter time means a faster adjustment to the original signal and a better detection for short and high peaks. (Heavily used in Hip-Hop, Pop).
+    /// A longer time means a less adjustment to the original signal and a better detection for long peaks. (Used in Rock or Punk etc.)
     ///
     /// sensitivity: Defines how much the current signal should be higher than the average signal to detect a peak.
     /// Mostly 1.5 times higher or 2 times higher values are used
     ///
-    /// gain_decay: Defines how fast the detector adjusts himself the output signal to the actual volume of the signal
+    /// gain_decay: Defines how fast the detector adjusts himself the output signal to the actual volume of the signal.
+    /// The release time of the gain in milliseconds
     ///
-    /// smoothing: Smoothed output signal if wished. Can be None
+    /// smoothing: The attack and release time of the output signal in milliseconds. (0.0, 0.0) disables the smoothing
     pub fn new(
         accuracy: f32,
         sensitivity: f32,
@@ -31,8 +34,8 @@ impl PeakDetector {
         smoothing: (f32, f32),
     ) -> PeakDetector {
         PeakDetector {
-            average_filter: ExponentialFilter::new(0.1, 0.1, accuracy),
-            gain_filter: ExponentialFilter::new(0.1, 0.9, gain_decay),
+            average_filter: ExponentialFilter::new(0.1, 100.0, accuracy),
+            gain_filter: ExponentialFilter::new(0.1, 5.0, gain_decay),
             smooth_filter: ExponentialFilter::new(0.1, smoothing.0, smoothing.1),
             sensitivity,
             on_peak: false,
@@ -52,27 +55,29 @@ impl PeakDetector {
         None
     }
 
-    pub fn update(&mut self, melbank: &[f32]) -> (f32, Option<bool>) {
+    /// Detect the peaks in the melbank.
+    /// The frame interval is the time between two frames in seconds.
+    pub fn update(&mut self, melbank: &[f32], frame_interval: f32) -> (f32, Option<bool>) {
 
         // Get the sum of all frequencies together
         let sum = melbank.iter().sum::<f32>();
 
-        let average_value = self.average_filter.update(sum);
+        let average_value = self.average_filter.update(sum, frame_interval);
 
         // If the current sum is (sensitivity) times bigger than the average curve, a peak will be delivered.
         let mut output_value = if sum > average_value*self.sensitivity { sum } else { 0.0 };
 
         // Do a maximum gain update
-        let current_gain = self.gain_filter.update(output_value);
+        let current_gain = self.gain_filter.update(output_value, frame_interval);
 
         // If the delivered value is two times smaller than the highest sum, the peak is too small and will not be counted
         if output_value < (current_gain / 2.0) { output_value = 0.0 }
 
         // Gain normalization
-        output_value = output_value / current_gain;
+        output_value /= current_gain;
 
         // Apply the smoothing filter
-        output_value = self.smooth_filter.update(output_value);
+        output_value = self.smooth_filter.update(output_value, frame_interval);
 
         // If a peak started or ended, notify
         (output_value, self.check_begin_and_end(output_value))
@@ -80,4 +85,93 @@ impl PeakDetector {
 
 
 
-}
\ No newline at end of file
+}
+
+/// The compression factor of the spectrum magnitudes: log(1 + γ|X|)
+const LOG_COMPRESSION: f32 = 100.0;
+/// The duration of the flux history for the adaptive threshold in seconds
+const THRESHOLD_WINDOW: f32 = 0.5;
+/// The flux has to be this many times larger than the median of the history
+const THRESHOLD_FACTOR: f32 = 1.5;
+/// The part of the recent maximum flux, which is added to the threshold, so noise is not detected in quiet parts
+const THRESHOLD_OFFSET: f32 = 0.05;
+/// The minimal time between two onsets in seconds
+const MIN_ONSET_INTERVAL: f32 = 0.05;
+
+/// The onset detection result of a single frame
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Onset {
+    /// The spectral flux relative to its recent maximum, from 0 to 1
+    pub strength: f32,
+    /// True, if a new onset started in this frame
+    pub detected: bool,
+}
+
+/// Onset detector based on the spectral flux.
+/// Unlike the peak detector, it also detects onsets which move energy between frequencies without changing the total energy.
+pub struct OnsetDetector {
+    /// The log compressed magnitudes of the last frame
+    previous: Vec<f32>,
+    /// The flux of the last frames for the adaptive threshold
+    history: VecDeque<f32>,
+    peak_filter: ExponentialFilter<f32>,
+    /// The time since the last onset in seconds
+    since_onset: f32,
+    above_threshold: bool,
+}
+
+impl OnsetDetector {
+
+    pub fn new() -> OnsetDetector {
+        OnsetDetector {
+            previous: Vec::new(),
+            history: VecDeque::new(),
+            peak_filter: ExponentialFilter::new(f32::EPSILON, 2.0, 10_000.0),
+            since_onset: MIN_ONSET_INTERVAL,
+            above_threshold: false,
+        }
+    }
+
+    /// Calculate the onset strength of the power spectrum and detect new onsets.
+    /// The frame interval is the time between two frames in seconds.
+    pub fn update(&mut self, spectrum: &[f32], frame_interval: f32) -> Onset {
+        let compressed = spectrum.iter()
+            .map(|power| (1.0 + LOG_COMPRESSION * power.sqrt()).ln())
+            .collect::<Vec<f32>>();
+
+        // Sum all rising magnitudes (half-wave rectification). If the spectrum size changed, there is no flux
+        let flux = if compressed.len() == self.previous.len() {
+            compressed.iter().zip(self.previous.iter())
+                .map(|(current, previous)| (current - previous).max(0.0))
+                .sum::<f32>()
+        } else {
+            0.0
+        };
+        self.previous = compressed;
+
+        // The adaptive threshold follows the median of the recent flux
+        let median = median(&mut self.history.iter().cloned().collect::<Vec<f32>>());
+        let peak = self.peak_filter.update(flux, frame_interval);
+        let threshold = THRESHOLD_FACTOR * median + THRESHOLD_OFFSET * peak;
+
+        let length = (THRESHOLD_WINDOW / frame_interval.max(f32::EPSILON)).ceil().max(1.0) as usize;
+        self.history.push_back(flux);
+        while self.history.len() > length {
+            self.history.pop_front();
+        }
+
+        // An onset is reported when the flux crosses the threshold
+        self.since_onset += frame_interval;
+        let above_threshold = flux > threshold;
+        let detected = above_threshold && !self.above_threshold && self.since_onset >= MIN_ONSET_INTERVAL;
+        self.above_threshold = above_threshold;
+        if detected {
+            self.since_onset = 0.0;
+        }
+
+        Onset {
+            strength: (flux / peak).clamp(0.0, 1.0),
+            detected,
+        }
+    }
+}
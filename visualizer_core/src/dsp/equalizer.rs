@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+/// A point of the graphic equalizer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EqPoint {
+    /// The frequency in Hz
+    pub frequency: f32,
+    /// The gain in dB. Positive values boost, negative values cut
+    pub gain: f32,
+}
+
+/// Graphic equalizer, which applies a gain curve on the bins of the filterbank.
+/// Between the points, the gain is interpolated on a logarithmic frequency axis. Outside of them, the nearest point is used.
+/// The points are shared between the clones, so copying the settings every frame stays cheap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphicEq {
+    /// The points of the gain curve, sorted by their frequency
+    points: Arc<Vec<EqPoint>>,
+}
+
+impl Default for GraphicEq {
+    /// A flat equalizer with a point at the center of every octave band
+    fn default() -> Self {
+        GraphicEq::new(
+            [31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0]
+                .map(|frequency| EqPoint { frequency, gain: 0.0 })
+                .to_vec()
+        )
+    }
+}
+
+impl GraphicEq {
+
+    /// Create an equalizer with the points in any order
+    pub fn new(mut points: Vec<EqPoint>) -> GraphicEq {
+        points.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+        GraphicEq { points: Arc::new(points) }
+    }
+
+    /// The points of the gain curve, sorted by their frequency
+    pub fn points(&self) -> &[EqPoint] {
+        &self.points
+    }
+
+    /// Replace the point at the index. The points are sorted again, so the point may get another index
+    pub fn set_point(&mut self, index: usize, point: EqPoint) {
+        let points = Arc::make_mut(&mut self.points);
+        if let Some(it) = points.get_mut(index) {
+            *it = point;
+            points.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+        }
+    }
+
+    /// Add a point at its position of the sorted points
+    pub fn add_point(&mut self, point: EqPoint) {
+        let points = Arc::make_mut(&mut self.points);
+        let index = points.partition_point(|it| it.frequency < point.frequency);
+        points.insert(index, point);
+    }
+
+    /// Remove the point at the index
+    pub fn remove_point(&mut self, index: usize) {
+        let points = Arc::make_mut(&mut self.points);
+        if index < points.len() {
+            points.remove(index);
+        }
+    }
+
+    /// True, if the equalizer doesn't change any bin
+    pub fn is_flat(&self) -> bool {
+        self.points.iter().all(|it| it.gain == 0.0)
+    }
+
+    /// Get the interpolated gain of the frequency in dB
+    pub fn gain(&self, frequency: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else { return 0.0 };
+        if frequency <= first.frequency { return first.gain; }
+        if frequency >= last.frequency { return last.gain; }
+
+        let upper = self.points.partition_point(|it| it.frequency < frequency);
+        let (lower, upper) = (self.points[upper - 1], self.points[upper]);
+        let position = (frequency.log2() - lower.frequency.log2()) / (upper.frequency.log2() - lower.frequency.log2());
+
+        lower.gain + (upper.gain - lower.gain) * position
+    }
+
+    /// Apply the gain curve on the melbank with the center frequency of every bin
+    pub fn apply(&self, melbank: &mut [f32], frequencies: &[f32]) {
+        if self.is_flat() { return; }
+
+        // The melbank contains the power of every bin
+        for (value, frequency) in melbank.iter_mut().zip(frequencies) {
+            *value *= 10.0_f32.powf(self.gain(*frequency) / 10.0);
+        }
+    }
+}
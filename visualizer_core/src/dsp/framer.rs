@@ -0,0 +1,63 @@
+/// Assembles analysis frames with a fixed window length from input chunks of any size.
+/// A new frame is produced every time hop_size new samples arrived,
+/// so consecutive frames overlap by window_size - hop_size samples.
+pub struct Framer {
+    /// Ring buffer with the latest window_size samples
+    ring: Vec<f32>,
+    /// The index in the ring where the next sample is written, which is also the oldest sample
+    position: usize,
+    /// The amount of samples, which arrived since the last frame
+    pending: usize,
+    hop_size: usize,
+}
+
+impl Framer {
+
+    /// Create a new framer.
+    /// The hop size is limited to the window size, so no samples are skipped
+    pub fn new(window_size: usize, hop_size: usize) -> Framer {
+        let (window_size, hop_size) = Self::limit(window_size, hop_size);
+
+        Framer {
+            ring: vec![0.0; window_size],
+            position: 0,
+            pending: 0,
+            hop_size,
+        }
+    }
+
+    /// Limit the window size and the hop size to valid values
+    fn limit(window_size: usize, hop_size: usize) -> (usize, usize) {
+        let window_size = window_size.max(2);
+        (window_size, hop_size.clamp(1, window_size))
+    }
+
+    /// Check if the framer was created with the given window size and hop size
+    pub fn has_config(&self, window_size: usize, hop_size: usize) -> bool {
+        Self::limit(window_size, hop_size) == (self.ring.len(), self.hop_size)
+    }
+
+    /// Add new samples to the framer and return all frames which were completed by them.
+    /// The samples of every frame are in chronological order.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        let mut frames = Vec::new();
+
+        for sample in samples {
+            self.ring[self.position] = *sample;
+            self.position = (self.position + 1) % self.ring.len();
+            self.pending += 1;
+
+            if self.pending == self.hop_size {
+                self.pending = 0;
+
+                // Unroll the ring, beginning with the oldest sample
+                let mut frame = Vec::with_capacity(self.ring.len());
+                frame.extend_from_slice(&self.ring[self.position..]);
+                frame.extend_from_slice(&self.ring[..self.position]);
+                frames.push(frame);
+            }
+        }
+
+        frames
+    }
+}
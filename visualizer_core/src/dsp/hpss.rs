@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use super::melbank::MelbankCache;
+use super::smoothing::median;
+
+/// The duration in milliseconds of the median filter over time, which enhances the harmonic parts
+const HARMONIC_KERNEL: f32 = 180.0;
+/// The bandwidth in Hz of the median filter over frequency, which enhances the percussive parts
+const PERCUSSIVE_KERNEL: f32 = 400.0;
+
+/// Harmonic/percussive source separation with median filters.
+/// Harmonic sounds are stable over time, so they survive a median filter over the last frames.
+/// Percussive sounds are broadband, so they survive a median filter over the frequencies.
+/// Both filtered spectra are used to build soft masks, which split the power spectrum.
+pub struct Hpss {
+    /// The last power spectra, the newest at the end
+    history: VecDeque<Vec<f32>>,
+    /// The melbank of the separated spectra
+    pub melbank: MelbankCache,
+}
+
+impl Hpss {
+
+    pub fn new() -> Hpss {
+        Hpss {
+            history: VecDeque::new(),
+            melbank: MelbankCache::new(),
+        }
+    }
+
+    /// Split the power spectrum into its harmonic and percussive part.
+    /// Both parts add up to the power spectrum.
+    /// The frequencies are the center frequencies of the fft bins and the frame interval is the time between two frames in seconds.
+    pub fn separate(&mut self, spectrum: &[f32], frequencies: &[f32], frame_interval: f32) -> (Vec<f32>, Vec<f32>) {
+        // Convert the kernels to frames and fft bins, so the separation doesn't depend on the hop size and the fft size
+        let frames = ((HARMONIC_KERNEL / 1000.0 / frame_interval).round() as usize).max(1);
+        let resolution = frequencies.get(1).copied().unwrap_or(1.0);
+        let half = (PERCUSSIVE_KERNEL / resolution / 2.0).round() as usize;
+
+        // Start again, if the size of the spectrum changed
+        if self.history.front().is_some_and(|it| it.len() != spectrum.len()) {
+            self.history.clear();
+        }
+        self.history.push_back(spectrum.to_vec());
+        while self.history.len() > frames {
+            self.history.pop_front();
+        }
+
+        let mut window = Vec::with_capacity(frames.max(2 * half + 1));
+
+        let mut harmonic = Vec::with_capacity(spectrum.len());
+        let mut percussive = Vec::with_capacity(spectrum.len());
+        for (i, power) in spectrum.iter().enumerate() {
+            // Median over time
+            window.clear();
+            window.extend(self.history.iter().map(|frame| frame[i]));
+            let h = median(&mut window);
+
+            // Median over frequency
+            window.clear();
+            window.extend_from_slice(&spectrum[i.saturating_sub(half)..(i + half + 1).min(spectrum.len())]);
+            let p = median(&mut window);
+
+            // Wiener-like soft masks
+            let total = h * h + p * p;
+            let mask = if total > 0.0 { h * h / total } else { 0.5 };
+            harmonic.push(power * mask);
+            percussive.push(power * (1.0 - mask));
+        }
+
+        (harmonic, percussive)
+    }
+}
+
+/// Split the bins of another filterbank with the masks of the separated spectra.
+/// Every bin gets the harmonic share of the fft bin, which is closest to its center frequency.
+pub fn split_bins(bins: &[f32], centers: &[f32], harmonic: &[f32], percussive: &[f32], frequencies: &[f32]) -> [Vec<f32>; 2] {
+    let resolution = frequencies.get(1).copied().unwrap_or(1.0);
+
+    let masks = centers.iter()
+        .map(|f| {
+            let i = ((f / resolution).round() as usize).min(harmonic.len().saturating_sub(1));
+            let (h, p) = (harmonic.get(i).copied().unwrap_or(0.0), percussive.get(i).copied().unwrap_or(0.0));
+            if h + p > 0.0 { h / (h + p) } else { 0.5 }
+        })
+        .collect::<Vec<f32>>();
+
+    [
+        bins.iter().zip(masks.iter()).map(|(bin, mask)| bin * mask).collect(),
+        bins.iter().zip(masks.iter()).map(|(bin, mask)| bin * (1.0 - mask)).collect(),
+    ]
+}
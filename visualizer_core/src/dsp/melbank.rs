@@ -1,5 +1,5 @@
+use std::ops::RangeInclusive;
 use realfft::num_traits::abs;
-use crate::math::linspace;
 
 /// Convert the frequency in heart to the mel scale
 fn heart_to_mel(f: f32) -> f32 {
@@ -11,29 +11,184 @@ fn mel_to_heart(m: f32) -> f32 {
     700.0 * (10.0_f32.powf(m/2595.0)  - 1.0)
 }
 
-/// Creates a list with all bins with their frequency (in mel) as their value
-fn get_mel_frequency_bins(n_bins: usize, min_frequency: f32, max_frequency: f32) -> Vec<f32> {
-    // Get the min and max frequency in the mel scale
-    let min = heart_to_mel(min_frequency);
-    let max = heart_to_mel(max_frequency);
+/// The frequency scale, on which the bins of the filterbank are evenly spaced
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FrequencyScale {
+    #[default]
+    Mel,
+    /// Bark scale of the critical bands (Traunmüller)
+    Bark,
+    /// Equivalent rectangular bandwidth scale (Glasberg and Moore)
+    Erb,
+    /// Fractional octave bands, which are centered on the base-2 frequencies of IEC 61260 around 1 kHz.
+    /// The amount of bins is given by the bands between the minimum and the maximum frequency
+    Logarithmic { bands_per_octave: usize },
+    Linear,
+}
+
+impl FrequencyScale {
+
+    /// Convert the frequency in heart to this scale
+    pub fn from_heart(&self, f: f32) -> f32 {
+        match self {
+            FrequencyScale::Mel => heart_to_mel(f),
+            FrequencyScale::Bark => 26.81 * f / (1960.0 + f) - 0.53,
+            FrequencyScale::Erb => 21.4 * (1.0 + 0.00437 * f).log10(),
+            // The logarithm is not defined at 0 Hz, so the scale starts at 1 Hz
+            FrequencyScale::Logarithmic { .. } => f.max(1.0).log2(),
+            FrequencyScale::Linear => f,
+        }
+    }
+
+    /// Convert the value of this scale to the frequency in heart
+    pub fn to_heart(&self, v: f32) -> f32 {
+        match self {
+            FrequencyScale::Mel => mel_to_heart(v),
+            FrequencyScale::Bark => 1960.0 * (v + 0.53) / (26.28 - v),
+            FrequencyScale::Erb => (10.0_f32.powf(v / 21.4) - 1.0) / 0.00437,
+            FrequencyScale::Logarithmic { .. } => 2.0_f32.powf(v),
+            FrequencyScale::Linear => v,
+        }
+    }
+
+    /// The amount of bins between the frequencies, if the scale defines its own bands.
+    /// None, if the bins can be spaced for any amount
+    pub fn band_count(&self, min_frequency: f32, max_frequency: f32) -> Option<usize> {
+        match self {
+            FrequencyScale::Logarithmic { bands_per_octave } => {
+                let bands = octave_bands(*bands_per_octave, min_frequency, max_frequency);
+                Some((bands.end() - bands.start() + 1).max(0) as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The reference frequency of the octave bands in Hz
+const OCTAVE_REFERENCE: f32 = 1000.0;
+
+/// The center of the octave band with the index on the log2 scale.
+/// With an odd amount of bands per octave, a band is centered on the reference frequency, otherwise the reference is a band edge
+fn octave_band_center(bands_per_octave: usize, index: i32) -> f32 {
+    let shift = if bands_per_octave.is_multiple_of(2) { 0.5 } else { 0.0 };
+    OCTAVE_REFERENCE.log2() + (index as f32 + shift) / bands_per_octave as f32
+}
+
+/// The indices of all octave bands with a center frequency between the frequencies
+fn octave_bands(bands_per_octave: usize, min_frequency: f32, max_frequency: f32) -> RangeInclusive<i32> {
+    let bands_per_octave = bands_per_octave.max(1);
+    // The position of a frequency in bands relative to the first band above the reference. The tolerance keeps bands, which are exactly on a limit
+    let position = |f: f32| (f.max(1.0).log2() - octave_band_center(bands_per_octave, 0)) * bands_per_octave as f32;
+
+    (position(min_frequency) - 1e-3).ceil() as i32..=(position(max_frequency) + 1e-3).floor() as i32
+}
+
+/// The normalization of the triangular filters
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FilterNormalization {
+    /// Every triangle has a peak weight of 1, so wide filters collect more energy than narrow ones
+    #[default]
+    None,
+    /// Every filter is divided by its area (Slaney), so white noise has the same energy in every bin
+    Area,
+    /// Every filter is divided by its largest weight, so the sampled triangles reach exactly 1
+    Peak,
+}
+
+impl FilterNormalization {
+
+    /// Normalize the weights of a single filter
+    fn apply(&self, weights: &mut [f32]) {
+        let divisor = match self {
+            FilterNormalization::None => return,
+            FilterNormalization::Area => weights.iter().sum::<f32>(),
+            FilterNormalization::Peak => weights.iter().cloned().fold(0.0, f32::max),
+        };
+
+        // Filters without any fft bin stay empty
+        if divisor > 0.0 {
+            weights.iter_mut().for_each(|it| *it /= divisor);
+        }
+    }
+}
+
+/// Creates a list with all bins with their frequency (in the given scale) as their value
+fn get_frequency_bins(scale: FrequencyScale, n_bins: usize, min_frequency: f32, max_frequency: f32) -> Vec<f32> {
+    // The octave bands have fixed centers, so the neighbour bands are the edges of the first and the last filter
+    if let FrequencyScale::Logarithmic { bands_per_octave } = scale {
+        let bands = octave_bands(bands_per_octave, min_frequency, max_frequency);
+        return (bands.start() - 1..=bands.end() + 1)
+            .map(|index| octave_band_center(bands_per_octave.max(1), index))
+            .collect();
+    }
+
+    // Get the min and max frequency in the scale
+    let min = scale.from_heart(min_frequency);
+    let max = scale.from_heart(max_frequency);
 
     // Calculate the space between the bins
-    let delta_mel = abs(max - min) / (n_bins + 1) as f32;
+    let delta = abs(max - min) / (n_bins + 1) as f32;
 
-    // With the information about the space between the bins, create a list with all the bins with their frequency (in mel) as value
+    // With the information about the space between the bins, create a list with all the bins with their frequency (in the scale) as value
     // Note that 2 extra bins are needed to create the matrix
     let mut frequency_bins: Vec<f32> = vec![0.0; n_bins + 2];
-    for b in 0..n_bins+2 {
+    for (b, bin) in frequency_bins.iter_mut().enumerate() {
         // bn = min + d * n
-        frequency_bins[b] = min + delta_mel * b as f32;
+        *bin = min + delta * b as f32;
     }
 
     frequency_bins
 }
 
-pub fn compute_mel_matrix(n_bins: usize, n_fft_bins: usize, min_freq: f32, max_freq: f32, sample_rate: u16) -> Vec<Vec<f32>> {
-    // Get the mel frequency bins
-    let bins = get_mel_frequency_bins(n_bins, min_freq, max_freq);
+/// Get the center frequency of every bin of the filterbank in Hz
+pub fn center_frequencies(scale: FrequencyScale, n_bins: usize, min_frequency: f32, max_frequency: f32) -> Vec<f32> {
+    let bins = get_frequency_bins(scale, n_bins, min_frequency, max_frequency);
+
+    bins[1..bins.len()-1].iter()
+        .map(|v| scale.to_heart(*v))
+        .collect()
+}
+
+/// Get the center frequency of every bin of a power spectrum with n_fft_bins bins.
+/// The spectrum of a fft with the size n has n/2 + 1 bins from 0 Hz to the nyquist frequency.
+pub fn fft_frequencies(n_fft_bins: usize, sample_rate: u32) -> Vec<f32> {
+    let fft_size = (n_fft_bins.max(2) - 1) * 2;
+    let resolution = sample_rate as f32 / fft_size as f32;
+
+    (0..n_fft_bins)
+        .map(|k| k as f32 * resolution)
+        .collect()
+}
+
+/// A single triangular filter of the filterbank matrix.
+/// Only the weights of the fft bins inside the triangle are stored, beginning at the start index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub start: usize,
+    pub weights: Vec<f32>,
+}
+
+impl Filter {
+    /// Multiply the fft frame with the weights of the filter
+    pub fn apply(&self, fft: &[f32]) -> f32 {
+        fft.iter().skip(self.start).zip(self.weights.iter())
+            .map(|(a, b)| *a * *b)
+            .sum::<f32>()
+    }
+}
+
+/// Compute the triangular filters, which are evenly spaced on the given frequency scale
+pub fn compute_filter_matrix(
+    scale: FrequencyScale,
+    normalization: FilterNormalization,
+    n_bins: usize,
+    n_fft_bins: usize,
+    min_freq: f32,
+    max_freq: f32,
+    sample_rate: u32
+) -> Vec<Filter> {
+    // Get the frequency bins in the scale
+    let bins = get_frequency_bins(scale, n_bins, min_freq, max_freq);
 
     // Subdivide the frequencies into center, lower and upper bands
     let center_bins = &bins[1..bins.len()-1];
@@ -42,41 +197,169 @@ pub fn compute_mel_matrix(n_bins: usize, n_fft_bins: usize, min_freq: f32, max_f
 
     // Transform the bands to its hz value
     let center_bins_hz = center_bins.iter()
-        .map(|m| mel_to_heart(*m)).collect::<Vec<f32>>();
+        .map(|v| scale.to_heart(*v)).collect::<Vec<f32>>();
     let lower_edges_hz = lower_edges.iter()
-        .map(|m| mel_to_heart(*m)).collect::<Vec<f32>>();
+        .map(|v| scale.to_heart(*v)).collect::<Vec<f32>>();
     let upper_edges_hz = upper_edges.iter()
-        .map(|m| mel_to_heart(*m)).collect::<Vec<f32>>();
+        .map(|v| scale.to_heart(*v)).collect::<Vec<f32>>();
 
     // Create a list with all frequency bins from the original fft
-    let frequencies = linspace(0.0, (sample_rate / 2) as f32, n_fft_bins);
+    let frequencies = fft_frequencies(n_fft_bins, sample_rate);
 
-    // Create the matrix with the mel bins as x and the fft bins as y
-    let mut matrix = vec![vec![0.0; n_fft_bins]; n_bins];
+    // Create one filter for every bin
+    let mut matrix = Vec::with_capacity(n_bins);
 
     // Go through every mel bin with the center, lower and upper bands
     // These bands are necessary to check if the frequency is in the mel bin and later calculate their weight
-    for (((i, center),lower), upper) in center_bins_hz.iter().enumerate().zip(lower_edges_hz).zip(upper_edges_hz) {
-
-        // Go through every fft bin
-        for (j, f) in frequencies.iter().enumerate() {
-            // Check if the current frequency f is in the left slope of the mel bin
-            if *f >= lower && *f <= *center {
-                // Calculate the weight of the frequency f
-                let weight = (*f - lower) / (*center - lower);
-                // Add the weight to the matrix
-                matrix[i][j] = weight;
-            }
+    for ((center, lower), upper) in center_bins_hz.iter().zip(lower_edges_hz).zip(upper_edges_hz) {
+        // Find the first fft bin inside the mel bin
+        // The frequencies are sorted, so only the fft bins between the lower and upper band have to be visited
+        let start = frequencies.partition_point(|f| *f < lower);
 
-            // Check also if the current frequency f is in the right slope
-            if *f >= *center && *f <= upper {
-                // Calculate the weight of the frequency f
-                let weight = (upper - *f) / (upper - *center);
-                // Add the weight to the matrix
-                matrix[i][j] = weight;
-            }
-        }
+        let mut weights = frequencies[start..].iter()
+            .take_while(|f| **f <= upper)
+            .map(|f| {
+                if *f <= *center {
+                    // The current frequency f is in the left slope of the mel bin
+                    (*f - lower) / (*center - lower)
+                } else {
+                    // The current frequency f is in the right slope
+                    (upper - *f) / (upper - *center)
+                }
+            })
+            .collect::<Vec<f32>>();
+        normalization.apply(&mut weights);
+
+        matrix.push(Filter { start, weights });
     }
 
     matrix
-}
\ No newline at end of file
+}
+
+/// Multiply the fft frame with every bin of the mel matrix
+pub fn apply_mel_matrix(matrix: &[Filter], fft: &[f32]) -> Vec<f32> {
+    // go through every mel bin
+    // and multiply the actual data with the pre-constructed mel bin
+    matrix.iter()
+        .map(|filter| filter.apply(fft))
+        .collect::<Vec<f32>>()
+}
+
+/// The parameters, a mel matrix was computed with
+#[derive(PartialEq)]
+struct MelbankKey {
+    n_bins: usize,
+    n_fft_bins: usize,
+    min_freq: f32,
+    max_freq: f32,
+    sample_rate: u32,
+    scale: FrequencyScale,
+    normalization: FilterNormalization,
+    /// The amount of fft bins of the bass spectrum and the crossover frequency of a multi-resolution melbank
+    bass: Option<(usize, f32)>,
+}
+
+/// Mel matrix, which is only computed again if its parameters change
+pub struct MelbankCache {
+    /// The frequency scale of the filters. Mel by default
+    pub scale: FrequencyScale,
+    /// The normalization of the filters. None by default
+    pub normalization: FilterNormalization,
+    key: Option<MelbankKey>,
+    matrix: Vec<Filter>,
+    /// The center frequency of every mel bin in Hz
+    frequencies: Vec<f32>,
+    /// The amount of mel bins at the beginning of the matrix, which are applied on the bass spectrum
+    bass_bins: usize,
+}
+
+impl MelbankCache {
+
+    pub fn new() -> MelbankCache {
+        MelbankCache {
+            scale: FrequencyScale::default(),
+            normalization: FilterNormalization::default(),
+            key: None,
+            matrix: Vec::new(),
+            frequencies: Vec::new(),
+            bass_bins: 0,
+        }
+    }
+
+    /// The center frequency of every bin of the last melbank in Hz
+    pub fn center_frequencies(&self) -> &[f32] {
+        &self.frequencies
+    }
+
+    /// Convert the fft frame to a melbank frame
+    pub fn apply(&mut self, fft: &[f32], min_freq: f32, max_freq: f32, bins: usize, sample_rate: u32) -> Vec<f32> {
+        let key = MelbankKey { n_bins: bins, n_fft_bins: fft.len(), min_freq, max_freq, sample_rate, scale: self.scale, normalization: self.normalization, bass: None };
+
+        if self.key.as_ref() != Some(&key) {
+            self.matrix = compute_filter_matrix(self.scale, self.normalization, bins, fft.len(), min_freq, max_freq, sample_rate);
+            self.frequencies = center_frequencies(self.scale, bins, min_freq, max_freq);
+            self.bass_bins = 0;
+            self.key = Some(key);
+        }
+
+        apply_mel_matrix(&self.matrix, fft)
+    }
+
+    /// Convert two fft frames with different resolutions to one melbank frame.
+    /// Every mel bin with a center frequency below the crossover frequency is taken from the bass spectrum,
+    /// which should be calculated from a longer window. All other mel bins are taken from the fft frame.
+    /// The frame lengths are the amount of samples of the short and the bass frame, which are needed to match the levels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_multi_resolution(
+        &mut self,
+        fft: &[f32],
+        bass_fft: &[f32],
+        frame_lengths: (usize, usize),
+        crossover: f32,
+        min_freq: f32,
+        max_freq: f32,
+        bins: usize,
+        sample_rate: u32
+    ) -> Vec<f32> {
+        let key = MelbankKey {
+            n_bins: bins,
+            n_fft_bins: fft.len(),
+            min_freq,
+            max_freq,
+            sample_rate,
+            scale: self.scale,
+            normalization: self.normalization,
+            bass: Some((bass_fft.len(), crossover))
+        };
+
+        if self.key.as_ref() != Some(&key) {
+            // The center frequencies are sorted, so the bass bins are always at the beginning
+            self.frequencies = center_frequencies(self.scale, bins, min_freq, max_freq);
+            self.bass_bins = self.frequencies.iter()
+                .take_while(|it| **it < crossover)
+                .count();
+
+            let bass_matrix = compute_filter_matrix(self.scale, self.normalization, bins, bass_fft.len(), min_freq, max_freq, sample_rate);
+            let matrix = compute_filter_matrix(self.scale, self.normalization, bins, fft.len(), min_freq, max_freq, sample_rate);
+            self.matrix = bass_matrix.into_iter().take(self.bass_bins)
+                .chain(matrix.into_iter().skip(self.bass_bins))
+                .collect();
+            self.key = Some(key);
+        }
+
+        // The power in a frequency range grows with the frame length and, summed over the fft bins, with the fft size.
+        // The area normalization divides by the amount of fft bins, so only the frame length remains
+        let (frame_length, bass_frame_length) = (frame_lengths.0 as f32, frame_lengths.1.max(1) as f32);
+        let scale = match self.normalization {
+            FilterNormalization::Area => frame_length / bass_frame_length,
+            FilterNormalization::None | FilterNormalization::Peak => {
+                let fft_ratio = (fft.len().max(2) - 1) as f32 / (bass_fft.len().max(2) - 1) as f32;
+                fft_ratio * frame_length / bass_frame_length
+            }
+        };
+
+        self.matrix.iter().enumerate()
+            .map(|(i, filter)| if i < self.bass_bins { filter.apply(bass_fft) * scale } else { filter.apply(fft) })
+            .collect()
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use log::{info, warn};
+use super::WindowFunction;
+
+/// The factor, which the noise profile is multiplied with before the subtraction.
+/// Values above 1 remove the fluctuations of the noise as well
+const OVER_SUBTRACTION: f32 = 1.5;
+/// The part of the original power, which always remains, so bins never become completely silent
+const SPECTRAL_FLOOR: f32 = 0.01;
+
+/// The settings of the analysis, which change the power spectrum of the noise.
+/// A profile is only subtracted from spectra, which were calculated with the same settings.
+/// The loudness weighting is applied after the noise reduction, so it doesn't change the profile
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NoiseSettings {
+    pub sample_rate: u32,
+    /// The amount of analyzed signals: the mono downmix and every channel, if the channels are analyzed separately
+    pub signals: usize,
+    /// The length of the short frame
+    pub window_size: usize,
+    /// The fft size of the short frame
+    pub fft_size: usize,
+    /// The length of the long bass frame. 0, if the multi-resolution analysis is disabled
+    pub bass_window_size: usize,
+    /// The fft size of the long bass frame. 0, if the multi-resolution analysis is disabled
+    pub bass_fft_size: usize,
+    pub pre_emphasis: f32,
+    pub window_function: WindowFunction,
+}
+
+impl NoiseSettings {
+
+    /// Write the settings as a single line of words
+    fn to_line(self) -> String {
+        let window = match self.window_function {
+            WindowFunction::Hann => "hann".to_string(),
+            WindowFunction::Hamming => "hamming".to_string(),
+            WindowFunction::BlackmanHarris => "blackman_harris".to_string(),
+            WindowFunction::Kaiser { beta } => format!("kaiser {}", beta),
+            WindowFunction::FlatTop => "flat_top".to_string(),
+            WindowFunction::Rectangular => "rectangular".to_string(),
+        };
+        format!(
+            "{} {} {} {} {} {} {} {}",
+            self.sample_rate, self.signals, self.window_size, self.fft_size, self.bass_window_size, self.bass_fft_size, self.pre_emphasis, window
+        )
+    }
+
+    /// Read the settings, which were written with [NoiseSettings::to_line]
+    fn from_line(line: &str) -> Option<NoiseSettings> {
+        let mut words = line.split_whitespace();
+        let sample_rate = words.next()?.parse().ok()?;
+        let signals = words.next()?.parse().ok()?;
+        let window_size = words.next()?.parse().ok()?;
+        let fft_size = words.next()?.parse().ok()?;
+        let bass_window_size = words.next()?.parse().ok()?;
+        let bass_fft_size = words.next()?.parse().ok()?;
+        let pre_emphasis = words.next()?.parse().ok()?;
+        let window_function = match words.next()? {
+            "hann" => WindowFunction::Hann,
+            "hamming" => WindowFunction::Hamming,
+            "blackman_harris" => WindowFunction::BlackmanHarris,
+            "kaiser" => WindowFunction::Kaiser { beta: words.next()?.parse().ok()? },
+            "flat_top" => WindowFunction::FlatTop,
+            "rectangular" => WindowFunction::Rectangular,
+            _ => return None,
+        };
+
+        Some(NoiseSettings { sample_rate, signals, window_size, fft_size, bass_window_size, bass_fft_size, pre_emphasis, window_function })
+    }
+}
+
+/// The average power spectrum of the noise of every analyzed signal of an input.
+/// Every channel has its own profile, because the downmix averages the uncorrelated noise of the channels
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseProfile {
+    /// The settings of the analysis, which the profile was learned with
+    pub settings: NoiseSettings,
+    /// The average noise power of every fft bin of every signal, beginning with the mono downmix
+    pub spectra: Vec<Vec<f32>>,
+    /// The average noise power of every fft bin of the long bass frame of every signal. Empty, if the multi-resolution analysis was disabled
+    pub bass_spectra: Vec<Vec<f32>>,
+}
+
+impl NoiseProfile {
+
+    /// Save the profile as a text file with one line for the settings and two lines for every signal, the spectrum and the bass spectrum
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let line = |spectrum: Option<&Vec<f32>>| spectrum.map_or(String::new(), |spectrum| spectrum.iter()
+            .map(|it| it.to_string())
+            .collect::<Vec<String>>()
+            .join(" "));
+
+        let mut content = format!("{}\n", self.settings.to_line());
+        for signal in 0..self.settings.signals {
+            content += &format!("{}\n{}\n", line(self.spectra.get(signal)), line(self.bass_spectra.get(signal)));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+
+    /// Load a profile, which was saved with [NoiseProfile::save]
+    pub fn load(path: &Path) -> io::Result<NoiseProfile> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid noise profile");
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+
+        let settings = lines.next()
+            .and_then(NoiseSettings::from_line)
+            .ok_or_else(invalid)?;
+        let mut spectrum = || lines.next()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|it| it.parse::<f32>().map_err(|_| invalid()))
+            .collect::<io::Result<Vec<f32>>>();
+        let (mut spectra, mut bass_spectra) = (Vec::new(), Vec::new());
+        for _ in 0..settings.signals {
+            spectra.push(spectrum()?);
+            bass_spectra.push(spectrum()?);
+        }
+        // Without the multi-resolution analysis, there are no bass spectra
+        if bass_spectra.iter().all(Vec::is_empty) {
+            bass_spectra.clear();
+        }
+
+        Ok(NoiseProfile { settings, spectra, bass_spectra })
+    }
+
+    /// Subtract the noise of the signal from its power spectrum of the short frame or, if bass is true, of the long bass frame.
+    /// The signal is the index of the spectrum, beginning with the mono downmix. The spectrum must be calculated with the settings of the profile.
+    /// The gain is the amplification of the input, which the spectrum was calculated with
+    pub fn subtract(&self, signal: usize, spectrum: &mut [f32], bass: bool, gain: f32) {
+        let noise = if bass { &self.bass_spectra } else { &self.spectra };
+        let Some(noise) = noise.get(signal) else { return };
+        if noise.len() != spectrum.len() { return; }
+
+        let scale = OVER_SUBTRACTION * gain * gain;
+        for (power, noise) in spectrum.iter_mut().zip(noise.iter()) {
+            *power = (*power - scale * noise).max(SPECTRAL_FLOOR * *power);
+        }
+    }
+}
+
+/// Averages the spectra of every signal of the input over the learning time
+struct NoiseLearner {
+    /// The remaining learning time in seconds
+    remaining: f32,
+    spectra: Vec<Vec<f32>>,
+    bass_spectra: Vec<Vec<f32>>,
+    frames: usize,
+}
+
+/// The noise reduction of a stream, which learns a noise profile and subtracts it from every spectrum.
+/// If a path is given, the profile is loaded from it and every newly learned profile is saved to it.
+pub struct NoiseReduction {
+    profile: Option<Arc<NoiseProfile>>,
+    learner: Option<NoiseLearner>,
+    path: Option<PathBuf>,
+    /// The settings, which were reported last for not matching the profile, so every mismatch is only logged once
+    mismatch: Option<NoiseSettings>,
+}
+
+impl NoiseReduction {
+
+    pub fn new(path: Option<PathBuf>) -> NoiseReduction {
+        let profile = path.as_deref()
+            .filter(|it| it.exists())
+            .and_then(|it| NoiseProfile::load(it)
+                .inspect_err(|e| warn!("Failed to load the noise profile {}: {}", it.display(), e))
+                .ok())
+            .map(Arc::new);
+
+        NoiseReduction {
+            profile,
+            learner: None,
+            path,
+            mismatch: None,
+        }
+    }
+
+    /// The current noise profile. None, if no profile was learned yet
+    pub fn profile(&self) -> Option<&NoiseProfile> {
+        self.profile.as_deref()
+    }
+
+    /// Start to learn a new noise profile for the duration in seconds.
+    /// The old profile is used, until the learning finished
+    pub fn learn(&mut self, duration: f32) {
+        info!("Learn the noise profile for {} s", duration);
+        self.learner = Some(NoiseLearner { remaining: duration, spectra: Vec::new(), bass_spectra: Vec::new(), frames: 0 });
+    }
+
+    /// True, while a noise profile is learned
+    pub fn is_learning(&self) -> bool {
+        self.learner.is_some()
+    }
+
+    /// Remove the noise profile and its file
+    pub fn clear(&mut self) {
+        self.profile = None;
+        self.learner = None;
+        if let Some(path) = self.path.as_deref()
+            && path.exists()
+            && let Err(e) = fs::remove_file(path) {
+            warn!("Failed to remove the noise profile {}: {}", path.display(), e);
+        }
+    }
+
+    /// Add the spectra of every signal to the learned profile, if a profile is learned.
+    /// The gain is the amplification of the input, which is removed again, so the profile doesn't depend on the gain control.
+    /// The frame interval is the time between two frames in seconds.
+    pub fn update(&mut self, spectra: &[Vec<f32>], bass_spectra: &[Vec<f32>], settings: &NoiseSettings, gain: f32, frame_interval: f32) {
+        let Some(learner) = self.learner.as_mut() else { return };
+
+        // Frames with a closed gate don't contain the noise
+        if gain > 0.0 {
+            let add = |sums: &mut Vec<Vec<f32>>, spectra: &[Vec<f32>]| {
+                sums.resize(spectra.len(), Vec::new());
+                for (sum, spectrum) in sums.iter_mut().zip(spectra) {
+                    sum.resize(spectrum.len(), 0.0);
+                    sum.iter_mut().zip(spectrum).for_each(|(sum, power)| *sum += power / (gain * gain));
+                }
+            };
+            add(&mut learner.spectra, spectra);
+            add(&mut learner.bass_spectra, bass_spectra);
+            learner.frames += 1;
+        }
+
+        learner.remaining -= frame_interval;
+        if learner.remaining > 0.0 { return; }
+
+        let Some(learner) = self.learner.take() else { return };
+        if learner.frames == 0 {
+            warn!("No noise was recorded, because the noise gate was closed");
+            return;
+        }
+
+        let average = |sums: Vec<Vec<f32>>| sums.into_iter()
+            .map(|sum| sum.into_iter().map(|it| it / learner.frames as f32).collect::<Vec<f32>>())
+            .collect::<Vec<Vec<f32>>>();
+        let profile = NoiseProfile {
+            settings: *settings,
+            spectra: average(learner.spectra),
+            bass_spectra: average(learner.bass_spectra),
+        };
+        info!("Learned the noise profile from {} frames", learner.frames);
+
+        if let Some(path) = self.path.as_deref()
+            && let Err(e) = profile.save(path) {
+            warn!("Failed to save the noise profile {}: {}", path.display(), e);
+        }
+        self.profile = Some(Arc::new(profile));
+        self.mismatch = None;
+    }
+
+    /// The noise profile, which can be subtracted from the spectra calculated with the settings.
+    /// None, if no profile was learned or if it was learned with other settings
+    pub fn matching_profile(&mut self, settings: &NoiseSettings) -> Option<Arc<NoiseProfile>> {
+        let profile = self.profile.as_ref()?;
+
+        if profile.settings != *settings {
+            if self.mismatch != Some(*settings) {
+                warn!("The noise profile was learned with {:?} and is not used for {:?}", profile.settings, settings);
+                self.mismatch = Some(*settings);
+            }
+            return None;
+        }
+        Some(profile.clone())
+    }
+}
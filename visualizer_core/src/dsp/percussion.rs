@@ -0,0 +1,129 @@
+use super::detection::PeakDetector;
+use super::melbank::MelbankCache;
+use super::smoothing::ExponentialFilter;
+
+/// The amount of melbank bins of every drum band
+const BAND_BINS: usize = 8;
+
+/// The frequency band, the peak detector and the smoothing (attack, release) of every drum. All times are in milliseconds
+const KICK: ((f32, f32), DetectorSettings) = ((40.0, 150.0), DetectorSettings { accuracy: 100.0, sensitivity: 1.5, gain_decay: 10_000.0, smoothing: (2.0, 30.0) });
+const SNARE: ((f32, f32), DetectorSettings) = ((1000.0, 5000.0), DetectorSettings { accuracy: 48.0, sensitivity: 1.5, gain_decay: 10_000.0, smoothing: (2.0, 21.0) });
+const HIHAT: ((f32, f32), DetectorSettings) = ((7000.0, 16000.0), DetectorSettings { accuracy: 21.0, sensitivity: 1.3, gain_decay: 10_000.0, smoothing: (2.0, 15.0) });
+
+/// The attack and release time in milliseconds of the background level of every band
+const BACKGROUND_SMOOTHING: (f32, f32) = (500.0, 500.0);
+/// A kick or hi-hat is only reported, if its band rises this many dB more than the part of the rise, which all bands share.
+/// Broadband hits like a snare raise every band by a similar amount and are only reported as snare
+const DOMINANCE: f32 = 6.0;
+
+struct DetectorSettings {
+    accuracy: f32,
+    sensitivity: f32,
+    gain_decay: f32,
+    smoothing: (f32, f32),
+}
+
+/// The detection result of a single drum in a frame
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct DrumHit {
+    /// The smoothed strength of the drum from 0 to 1
+    pub strength: f32,
+    /// True, if a new hit started in this frame
+    pub detected: bool,
+}
+
+/// The detection results of all drums in a frame
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Percussion {
+    pub kick: DrumHit,
+    pub snare: DrumHit,
+    pub hihat: DrumHit,
+}
+
+/// Detects the hits of a single drum with a peak detector on the melbank of its frequency band
+struct DrumDetector {
+    range: (f32, f32),
+    melbank: MelbankCache,
+    peak_detector: PeakDetector,
+    /// The smoothed power of the band, which the rise of a hit is measured against
+    background: ExponentialFilter<f32>,
+    /// True, if the last hit was only part of a broadband hit. The drum is silent until its next own hit
+    suppressed: bool,
+}
+
+impl DrumDetector {
+
+    fn new((range, settings): ((f32, f32), DetectorSettings)) -> DrumDetector {
+        DrumDetector {
+            range,
+            melbank: MelbankCache::new(),
+            peak_detector: PeakDetector::new(settings.accuracy, settings.sensitivity, settings.gain_decay, settings.smoothing),
+            background: ExponentialFilter::new(0.0, BACKGROUND_SMOOTHING.0, BACKGROUND_SMOOTHING.1),
+            suppressed: false,
+        }
+    }
+
+    /// Silence the hit and the decay after it, if the hit is only part of a broadband hit
+    fn gate(&mut self, hit: DrumHit, broadband: bool) -> DrumHit {
+        if hit.detected {
+            self.suppressed = broadband;
+        }
+        if self.suppressed { DrumHit::default() } else { hit }
+    }
+
+    /// Detect a hit in the band and get the rise of the band power above its background in dB
+    fn update(&mut self, spectrum: &[f32], sample_rate: u32, frame_interval: f32) -> (DrumHit, f32) {
+        let melbank = self.melbank.apply(spectrum, self.range.0, self.range.1, BAND_BINS, sample_rate);
+        let (strength, update) = self.peak_detector.update(&melbank, frame_interval);
+
+        // The rise is relative to the band itself, so it doesn't depend on the tilt of the spectrum
+        let power = melbank.iter().sum::<f32>();
+        let background = self.background.update(power, frame_interval);
+        let rise = 10.0 * (power.max(f32::MIN_POSITIVE) / background.max(f32::MIN_POSITIVE)).log10();
+
+        let hit = DrumHit {
+            strength: strength.clamp(0.0, 1.0),
+            detected: update == Some(true),
+        };
+        (hit, rise)
+    }
+}
+
+/// Classifies the transients of the power spectrum into kick, snare and hi-hat hits by their frequency band.
+/// Every band is measured against its own background. A hit, which raises all bands, is only reported as snare,
+/// so a kick or hi-hat must rise clearly more than the weaker of the two other bands.
+pub struct PercussionAnalyzer {
+    kick: DrumDetector,
+    snare: DrumDetector,
+    hihat: DrumDetector,
+}
+
+impl PercussionAnalyzer {
+
+    pub fn new() -> PercussionAnalyzer {
+        PercussionAnalyzer {
+            kick: DrumDetector::new(KICK),
+            snare: DrumDetector::new(SNARE),
+            hihat: DrumDetector::new(HIHAT),
+        }
+    }
+
+    /// Detect the drum hits in the power spectrum.
+    /// The frame interval is the time between two frames in seconds.
+    pub fn update(&mut self, spectrum: &[f32], sample_rate: u32, frame_interval: f32) -> Percussion {
+        let (kick, kick_rise) = self.kick.update(spectrum, sample_rate, frame_interval);
+        let (snare, snare_rise) = self.snare.update(spectrum, sample_rate, frame_interval);
+        let (hihat, hihat_rise) = self.hihat.update(spectrum, sample_rate, frame_interval);
+
+        // Only the rise, which all bands share, belongs to a broadband hit.
+        // Simultaneous hits of a kick and a snare leave the hi-hat band quiet, so both of them remain
+        let kick_broadband = kick_rise - snare_rise.min(hihat_rise) < DOMINANCE;
+        let hihat_broadband = hihat_rise - snare_rise.min(kick_rise) < DOMINANCE;
+
+        Percussion {
+            kick: self.kick.gate(kick, kick_broadband),
+            snare,
+            hihat: self.hihat.gate(hihat, hihat_broadband),
+        }
+    }
+}
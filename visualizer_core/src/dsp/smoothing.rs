@@ -1,28 +1,44 @@
+use std::collections::VecDeque;
+
 /// Exponential filter for the types f32 and Vec<f32>
-/// with two individual factors for rise or decay
+/// with two individual time constants for rise (attack) or decay (release).
+/// The time constants are converted to smoothing factors with the actual frame interval,
+/// so the filter behaves the same at every frame rate.
 pub struct ExponentialFilter<T> {
     last: T,
-    alpha_rise: f32,
-    alpha_decay: f32
+    /// The attack time in milliseconds
+    attack: f32,
+    /// The release time in milliseconds
+    release: f32,
 }
 
 impl <T> ExponentialFilter<T> {
-    /// Create a new exponential filter with two individual factors for rise or decay
-    pub fn new(last: T, alpha_rise: f32, alpha_decay: f32) -> ExponentialFilter<T> {
+    /// Create a new exponential filter with two individual time constants in milliseconds for rise or decay
+    pub fn new(last: T, attack: f32, release: f32) -> ExponentialFilter<T> {
         ExponentialFilter {
             last,
-            alpha_rise,
-            alpha_decay
+            attack,
+            release
         }
     }
 }
 
+/// Get the smoothing factor for the time constant in milliseconds and the frame interval in seconds.
+/// After the time constant, the filter reached 63% of a step.
+pub fn alpha(time: f32, frame_interval: f32) -> f32 {
+    if time <= 0.0 { return 1.0; }
+
+    1.0 - (-frame_interval * 1000.0 / time).exp()
+}
+
 impl ExponentialFilter<f32> {
-    /// Calculate the next smoothed value
-    pub fn update(&mut self, value: f32) -> f32 {
-        // If the new value is bigger than the last, another factor will be used
+    /// Calculate the next smoothed value.
+    /// The frame interval is the time since the last value in seconds
+    pub fn update(&mut self, value: f32, frame_interval: f32) -> f32 {
+        // If the new value is bigger than the last, another time constant will be used
         // A faster rise and a shorter decay is usually wanted
-        let alpha = if value > self.last { self.alpha_rise } else { self.alpha_decay };
+        let time = if value > self.last { self.attack } else { self.release };
+        let alpha = alpha(time, frame_interval);
 
         // Use exponential smoothing for the signal
         // y = a*xt + (1 - a) * xt-1
@@ -34,24 +50,28 @@ impl ExponentialFilter<f32> {
     pub fn gain_settings() -> Self {
         Self {
             last: 0.1,
-            alpha_rise: 0.99,
-            alpha_decay: 0.1,
+            attack: 2.0,
+            release: 100.0,
         }
     }
 }
 
 impl ExponentialFilter<Vec<f32>> {
-    /// Calculate the next smoothed value
-    pub fn update(&mut self, values: &mut [f32]) {
+    /// Calculate the next smoothed value.
+    /// The frame interval is the time since the last value in seconds
+    pub fn update(&mut self, values: &mut [f32], frame_interval: f32) {
         // If the size of the input bins are changing, resize the internal value
         if values.len() != self.last.len() {
             self.last.resize(values.len(), 0.0);
         }
 
+        let alpha_rise = alpha(self.attack, frame_interval);
+        let alpha_decay = alpha(self.release, frame_interval);
+
         // Dot he same exponential smoothing as the implementation for the f32 value, but now for every value in the vector
         for (last, value) in self.last.iter_mut().zip(values.iter_mut()) {
             // A faster rise and a shorter decay is usually wanted
-            let alpha = if *value > *last { self.alpha_rise } else { self.alpha_decay };
+            let alpha = if *value > *last { alpha_rise } else { alpha_decay };
 
             // Use exponential smoothing for the signal
             // y = a*xt + (1 - a) * xt-1
@@ -66,8 +86,260 @@ impl ExponentialFilter<Vec<f32>> {
     pub fn smoothing_settings() -> Self {
         Self {
             last: vec![0.0; 100],
-            alpha_rise: 0.99,
-            alpha_decay: 0.05,
+            attack: 2.0,
+            release: 200.0,
+        }
+    }
+}
+
+/// A filter, which smooths a signal of the type T over time.
+/// It is implemented for single values (f32) and for every bin of a frame ([f32]), so the effects can choose their filter.
+pub trait Smoothing<T: ?Sized>: Send {
+    /// Replace the value with the next smoothed value.
+    /// The frame interval is the time since the last value in seconds
+    fn smooth(&mut self, value: &mut T, frame_interval: f32);
+}
+
+/// The filter, which smooths the bins of the effects over time
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SmoothingMethod {
+    /// Exponential smoothing with a fast attack and a slow release
+    #[default]
+    Exponential,
+    /// One-euro filter, which smooths slow changes and follows fast changes
+    OneEuro,
+    /// Moving median, which removes short spikes
+    Median,
+    /// Kalman filter with a random walk model
+    Kalman,
+}
+
+impl SmoothingMethod {
+    /// Create the filter for the bins of a frame with the default settings of the method
+    pub fn create(&self) -> Box<dyn Smoothing<[f32]>> {
+        match self {
+            SmoothingMethod::Exponential => Box::new(ExponentialFilter::smoothing_settings()),
+            SmoothingMethod::OneEuro => Box::new(OneEuroFilter::new(Vec::new(), 1.0, 0.1, 1.0)),
+            SmoothingMethod::Median => Box::new(MedianFilter::<Vec<f32>>::new(100.0)),
+            SmoothingMethod::Kalman => Box::new(KalmanFilter::new(Vec::new(), 1.0, 0.05)),
+        }
+    }
+}
+
+impl Smoothing<f32> for ExponentialFilter<f32> {
+    fn smooth(&mut self, value: &mut f32, frame_interval: f32) {
+        *value = self.update(*value, frame_interval);
+    }
+}
+
+impl Smoothing<[f32]> for ExponentialFilter<Vec<f32>> {
+    fn smooth(&mut self, values: &mut [f32], frame_interval: f32) {
+        self.update(values, frame_interval);
+    }
+}
+
+/// One-euro filter (Casiez et al.) with an adaptive cutoff frequency.
+/// Slow signals are smoothed heavily against jitter and fast changes barely lag, because the cutoff rises with the speed of the signal.
+pub struct OneEuroFilter<T> {
+    last: T,
+    /// The smoothed derivative of the signal per second
+    derivative: T,
+    /// The cutoff frequency in Hz of a signal at rest
+    min_cutoff: f32,
+    /// How much the cutoff frequency rises with the speed of the signal
+    beta: f32,
+    /// The cutoff frequency in Hz of the derivative
+    derivative_cutoff: f32,
+}
+
+impl <T: Default> OneEuroFilter<T> {
+    /// Create a new one-euro filter with the cutoff frequencies in Hz
+    pub fn new(last: T, min_cutoff: f32, beta: f32, derivative_cutoff: f32) -> OneEuroFilter<T> {
+        OneEuroFilter {
+            last,
+            derivative: T::default(),
+            min_cutoff,
+            beta,
+            derivative_cutoff,
+        }
+    }
+}
+
+impl <T> OneEuroFilter<T> {
+    /// Get the smoothing factor of a low pass filter with the cutoff frequency in Hz
+    fn alpha(cutoff: f32, frame_interval: f32) -> f32 {
+        let tau = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+        1.0 / (1.0 + tau / frame_interval.max(f32::EPSILON))
+    }
+
+    /// Calculate the next smoothed value of a single signal
+    fn step(&self, last: &mut f32, derivative: &mut f32, value: f32, frame_interval: f32) -> f32 {
+        // Smooth the speed of the signal, so the cutoff doesn't jitter
+        let alpha = Self::alpha(self.derivative_cutoff, frame_interval);
+        let speed = (value - *last) / frame_interval.max(f32::EPSILON);
+        *derivative = alpha * speed + (1.0 - alpha) * *derivative;
+
+        // The faster the signal changes, the higher the cutoff
+        let cutoff = self.min_cutoff + self.beta * derivative.abs();
+        let alpha = Self::alpha(cutoff, frame_interval);
+        *last = alpha * value + (1.0 - alpha) * *last;
+
+        *last
+    }
+}
+
+impl Smoothing<f32> for OneEuroFilter<f32> {
+    fn smooth(&mut self, value: &mut f32, frame_interval: f32) {
+        let (mut last, mut derivative) = (self.last, self.derivative);
+        *value = self.step(&mut last, &mut derivative, *value, frame_interval);
+        (self.last, self.derivative) = (last, derivative);
+    }
+}
+
+impl Smoothing<[f32]> for OneEuroFilter<Vec<f32>> {
+    fn smooth(&mut self, values: &mut [f32], frame_interval: f32) {
+        // If the size of the input bins are changing, resize the internal values
+        if values.len() != self.last.len() || values.len() != self.derivative.len() {
+            self.last.resize(values.len(), 0.0);
+            self.derivative.resize(values.len(), 0.0);
+        }
+
+        let mut last = std::mem::take(&mut self.last);
+        let mut derivative = std::mem::take(&mut self.derivative);
+        for ((last, derivative), value) in last.iter_mut().zip(derivative.iter_mut()).zip(values.iter_mut()) {
+            *value = self.step(last, derivative, *value, frame_interval);
+        }
+        (self.last, self.derivative) = (last, derivative);
+    }
+}
+
+/// Moving median over the last values.
+/// Removes short spikes completely, while steps pass unchanged after half of the window.
+pub struct MedianFilter<T> {
+    history: VecDeque<T>,
+    /// The length of the window in milliseconds
+    window: f32,
+}
+
+impl <T> MedianFilter<T> {
+    /// Create a new moving median with the length of the window in milliseconds
+    pub fn new(window: f32) -> MedianFilter<T> {
+        MedianFilter {
+            history: VecDeque::new(),
+            window,
+        }
+    }
+
+    /// Add the value to the history and drop every value, which is outside the window
+    fn push(&mut self, value: T, frame_interval: f32) {
+        let length = (self.window / 1000.0 / frame_interval.max(f32::EPSILON)).round().max(1.0) as usize;
+
+        self.history.push_back(value);
+        while self.history.len() > length {
+            self.history.pop_front();
+        }
+    }
+}
+
+/// Get the median of the values. For an even amount, the mean of the two middle values is used.
+/// The values are sorted in place and without any value the median is 0
+pub fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() { return 0.0; }
+
+    values.sort_by(f32::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+impl Smoothing<f32> for MedianFilter<f32> {
+    fn smooth(&mut self, value: &mut f32, frame_interval: f32) {
+        self.push(*value, frame_interval);
+        *value = median(&mut self.history.iter().cloned().collect::<Vec<f32>>());
+    }
+}
+
+impl Smoothing<[f32]> for MedianFilter<Vec<f32>> {
+    fn smooth(&mut self, values: &mut [f32], frame_interval: f32) {
+        // If the size of the input bins are changing, the old frames can't be used anymore
+        if self.history.front().is_some_and(|it| it.len() != values.len()) {
+            self.history.clear();
+        }
+        self.push(values.to_vec(), frame_interval);
+
+        // Take the median of every bin
+        let mut bin = Vec::with_capacity(self.history.len());
+        for (i, value) in values.iter_mut().enumerate() {
+            bin.clear();
+            bin.extend(self.history.iter().map(|frame| frame[i]));
+            *value = median(&mut bin);
+        }
+    }
+}
+
+/// One-dimensional Kalman filter, which models the signal as a random walk.
+/// The smoothing adapts to the ratio between the process noise and the measurement noise.
+pub struct KalmanFilter<T> {
+    estimate: T,
+    /// The variance of the estimate
+    error: T,
+    /// The variance, which the signal changes per second
+    process_noise: f32,
+    /// The variance of the noise of every measurement
+    measurement_noise: f32,
+}
+
+impl <T: Default> KalmanFilter<T> {
+    /// Create a new Kalman filter with the process noise (variance per second) and the measurement noise (variance)
+    pub fn new(last: T, process_noise: f32, measurement_noise: f32) -> KalmanFilter<T> {
+        KalmanFilter {
+            estimate: last,
+            error: T::default(),
+            process_noise,
+            measurement_noise,
+        }
+    }
+}
+
+impl <T> KalmanFilter<T> {
+    /// Calculate the next estimate of a single signal
+    fn step(&self, estimate: &mut f32, error: &mut f32, value: f32, frame_interval: f32) -> f32 {
+        // Predict: the signal could have moved since the last frame
+        *error += self.process_noise * frame_interval;
+
+        // Update: weight the measurement by the uncertainty of the estimate
+        let gain = *error / (*error + self.measurement_noise).max(f32::EPSILON);
+        *estimate += gain * (value - *estimate);
+        *error *= 1.0 - gain;
+
+        *estimate
+    }
+}
+
+impl Smoothing<f32> for KalmanFilter<f32> {
+    fn smooth(&mut self, value: &mut f32, frame_interval: f32) {
+        let (mut estimate, mut error) = (self.estimate, self.error);
+        *value = self.step(&mut estimate, &mut error, *value, frame_interval);
+        (self.estimate, self.error) = (estimate, error);
+    }
+}
+
+impl Smoothing<[f32]> for KalmanFilter<Vec<f32>> {
+    fn smooth(&mut self, values: &mut [f32], frame_interval: f32) {
+        // If the size of the input bins are changing, the new bins start without any knowledge
+        if values.len() != self.estimate.len() || values.len() != self.error.len() {
+            self.estimate.resize(values.len(), 0.0);
+            self.error.resize(values.len(), self.measurement_noise);
+        }
+
+        let mut estimate = std::mem::take(&mut self.estimate);
+        let mut error = std::mem::take(&mut self.error);
+        for ((estimate, error), value) in estimate.iter_mut().zip(error.iter_mut()).zip(values.iter_mut()) {
+            *value = self.step(estimate, error, *value, frame_interval);
         }
+        (self.estimate, self.error) = (estimate, error);
     }
 }
@@ -0,0 +1,120 @@
+/// The frequencies of the ISO 226:2003 equal-loudness contours in Hz
+const ISO_226_FREQUENCIES: [f32; 29] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0, 500.0,
+    630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0, 8000.0, 10000.0, 12500.0
+];
+/// The exponent of the loudness perception
+const ISO_226_AF: [f32; 29] = [
+    0.532, 0.506, 0.480, 0.455, 0.432, 0.409, 0.387, 0.367, 0.349, 0.330, 0.315, 0.301, 0.288, 0.276, 0.267,
+    0.259, 0.253, 0.250, 0.246, 0.244, 0.243, 0.243, 0.243, 0.242, 0.242, 0.245, 0.254, 0.271, 0.301
+];
+/// The magnitude of the linear transfer function in dB
+const ISO_226_LU: [f32; 29] = [
+    -31.6, -27.2, -23.0, -19.1, -15.9, -13.0, -10.3, -8.1, -6.2, -4.5, -3.1, -2.0, -1.1, -0.4, 0.0,
+    0.3, 0.5, 0.0, -2.7, -4.1, -1.0, 1.7, 2.5, 1.2, -2.1, -7.1, -11.2, -10.7, -3.1
+];
+/// The threshold of hearing in dB
+const ISO_226_TF: [f32; 29] = [
+    78.5, 68.7, 59.5, 51.1, 44.0, 37.5, 31.5, 26.5, 22.1, 17.9, 14.4, 11.4, 8.6, 6.2, 4.4,
+    3.0, 2.2, 2.4, 3.5, 1.7, -1.3, -4.2, -6.0, -5.4, -1.5, 6.0, 12.6, 13.9, 12.3
+];
+
+/// The weighting of the power spectrum, so the spectrum matches the perceived loudness
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum LoudnessWeighting {
+    #[default]
+    None,
+    /// A-weighting (IEC 61672), which approximates the loudness of quiet sounds
+    AWeighting,
+    /// The inverse of the ISO 226:2003 equal-loudness contour with the loudness level in phon.
+    /// Louder levels have flatter contours
+    Iso226 { phon: f32 },
+}
+
+impl LoudnessWeighting {
+
+    /// Get the weight of the frequency in dB, which is 0 at 1 kHz
+    pub fn db(&self, frequency: f32) -> f32 {
+        match self {
+            LoudnessWeighting::None => 0.0,
+            LoudnessWeighting::AWeighting => a_weighting(frequency),
+            LoudnessWeighting::Iso226 { phon } => iso_226(frequency, *phon),
+        }
+    }
+
+    /// Get the factor for the power of every frequency
+    pub fn weights(&self, frequencies: &[f32]) -> Vec<f32> {
+        frequencies.iter()
+            .map(|f| 10.0_f32.powf(self.db(*f) / 10.0))
+            .collect()
+    }
+}
+
+/// The A-weighting of the frequency in dB
+fn a_weighting(f: f32) -> f32 {
+    if f <= 0.0 { return f32::NEG_INFINITY; }
+
+    let f2 = f * f;
+    let ra = 12194.0_f32.powi(2) * f2 * f2
+        / ((f2 + 20.6_f32.powi(2)) * ((f2 + 107.7_f32.powi(2)) * (f2 + 737.9_f32.powi(2))).sqrt() * (f2 + 12194.0_f32.powi(2)));
+
+    20.0 * ra.log10() + 2.0
+}
+
+/// The sound pressure level in dB, which is needed at the frequency of the table index to be perceived as loud as the loudness level
+fn iso_226_level(i: usize, phon: f32) -> f32 {
+    let af = 4.47e-3 * (10.0_f32.powf(0.025 * phon) - 1.15)
+        + (0.4 * 10.0_f32.powf((ISO_226_TF[i] + ISO_226_LU[i]) / 10.0 - 9.0)).powf(ISO_226_AF[i]);
+
+    10.0 / ISO_226_AF[i] * af.log10() - ISO_226_LU[i] + 94.0
+}
+
+/// The inverse equal-loudness contour of the frequency in dB.
+/// Between the frequencies of the table, the contour is interpolated on a logarithmic frequency axis,
+/// outside of the table the nearest value is used.
+fn iso_226(f: f32, phon: f32) -> f32 {
+    // The contours are only defined from 20 to 90 phon
+    let phon = phon.clamp(20.0, 90.0);
+    let upper = ISO_226_FREQUENCIES.partition_point(|it| *it < f).clamp(1, ISO_226_FREQUENCIES.len() - 1);
+    let lower = upper - 1;
+
+    let position = (f.max(ISO_226_FREQUENCIES[0]).log2() - ISO_226_FREQUENCIES[lower].log2())
+        / (ISO_226_FREQUENCIES[upper].log2() - ISO_226_FREQUENCIES[lower].log2());
+    let position = position.clamp(0.0, 1.0);
+    let level = iso_226_level(lower, phon) * (1.0 - position) + iso_226_level(upper, phon) * position;
+
+    // Relative to 1 kHz, where the level equals the loudness level
+    iso_226_level(17, phon) - level
+}
+
+/// The weights of the power spectrum, which are only computed again if the weighting or the frequencies change
+pub struct WeightingCache {
+    /// The weighting, the amount of bins, the first and the last frequency and the sample rate
+    key: Option<(LoudnessWeighting, usize, f32, f32, u32)>,
+    weights: Vec<f32>,
+}
+
+impl WeightingCache {
+
+    pub fn new() -> WeightingCache {
+        WeightingCache {
+            key: None,
+            weights: Vec::new(),
+        }
+    }
+
+    /// Weight every bin of the power spectrum with its frequency
+    pub fn apply(&mut self, spectrum: &mut [f32], frequencies: &[f32], weighting: LoudnessWeighting, sample_rate: u32) {
+        if weighting == LoudnessWeighting::None { return; }
+
+        // The bins of the constant-Q transform move with the minimum frequency, so the frequency range is part of the key
+        let key = (weighting, frequencies.len(), frequencies.first().copied().unwrap_or(0.0), frequencies.last().copied().unwrap_or(0.0), sample_rate);
+        if self.key != Some(key) {
+            self.weights = weighting.weights(frequencies);
+            self.key = Some(key);
+        }
+
+        spectrum.iter_mut().zip(self.weights.iter())
+            .for_each(|(power, weight)| *power *= weight);
+    }
+}
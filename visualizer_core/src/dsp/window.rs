@@ -0,0 +1,66 @@
+use std::f32::consts::PI;
+
+use crate::math::bessel_i0;
+
+/// The window function, which is applied on every frame before the fft.
+/// Windows with a low leakage (Blackman-Harris, Kaiser with a high beta) separate loud and quiet frequencies better,
+/// windows with a narrow main lobe (Rectangular, Hamming, Hann) separate close frequencies better.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum WindowFunction {
+    #[default]
+    Hann,
+    Hamming,
+    BlackmanHarris,
+    /// Kaiser window. A higher beta reduces the leakage, but widens the main lobe
+    Kaiser { beta: f32 },
+    /// Flat top window, which measures the amplitude of a frequency most accurately
+    FlatTop,
+    /// No window at all
+    Rectangular,
+}
+
+impl WindowFunction {
+
+    /// Create the window with the given length.
+    /// The window is divided by its coherent gain, so a sinusoid has the same amplitude with every window function.
+    pub fn create(&self, len: usize) -> Vec<f32> {
+        // The position of every sample, from 0 to 1
+        let m = (len.max(2) - 1) as f32;
+        let mut window = (0..len)
+            .map(|n| self.weight(n as f32 / m))
+            .collect::<Vec<f32>>();
+
+        // Amplitude correction
+        let gain = window.iter().sum::<f32>() / len as f32;
+        if gain > 0.0 {
+            window.iter_mut().for_each(|it| *it /= gain);
+        }
+
+        window
+    }
+
+    /// Calculate the weight of the window at the position x from 0 to 1
+    fn weight(&self, x: f32) -> f32 {
+        match self {
+            WindowFunction::Hann => Self::cosine_sum(x, &[0.5, 0.5]),
+            WindowFunction::Hamming => Self::cosine_sum(x, &[0.54, 0.46]),
+            WindowFunction::BlackmanHarris => Self::cosine_sum(x, &[0.35875, 0.48829, 0.14128, 0.01168]),
+            WindowFunction::FlatTop => Self::cosine_sum(x, &[0.215_578_95, 0.416_631_6, 0.277_263_16, 0.083_578_95, 0.006_947_368]),
+            WindowFunction::Kaiser { beta } => {
+                let r = 2.0 * x - 1.0;
+                bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(*beta)
+            }
+            WindowFunction::Rectangular => 1.0,
+        }
+    }
+
+    /// Generalized cosine window: w(x) = a0 - a1*cos(2πx) + a2*cos(4πx) - ...
+    fn cosine_sum(x: f32, coefficients: &[f32]) -> f32 {
+        coefficients.iter().enumerate()
+            .map(|(k, a)| {
+                let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                sign * a * (2.0 * PI * k as f32 * x).cos()
+            })
+            .sum()
+    }
+}
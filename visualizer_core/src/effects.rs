@@ -1,6 +1,6 @@
 use super::stream::channel::{Frame, ViewFrame};
 use super::stream::Settings;
-use super::dsp::ExponentialFilter;
+use super::dsp::{Beat, ExponentialFilter, Key, Onset, Percussion, Smoothing, SmoothingMethod};
 use super::math::transpose;
 
 // All effects
@@ -11,6 +11,13 @@ mod fft;
 mod color_spectrum;
 mod energy;
 mod bass;
+mod stereo;
+mod notes;
+mod chroma;
+mod onset;
+mod beat;
+mod drums;
+mod separated;
 
 // Re-export all the effects
 pub use melbank::MelbankEffect;
@@ -20,66 +27,123 @@ pub use fft::FftEffect;
 pub use color_spectrum::ColorSpectrumEffect;
 pub use energy::EnergyEffect;
 pub use bass::BassEffect;
+pub use stereo::StereoSpectrumEffect;
+pub use notes::NoteSpectrumEffect;
+pub use chroma::ChromaEffect;
+pub use onset::OnsetEffect;
+pub use beat::BeatEffect;
+pub use drums::DrumsEffect;
+pub use separated::SeparatedSpectrumEffect;
 
 type GainFilter = ExponentialFilter<f32>;
-type SmoothingFilter = ExponentialFilter<Vec<f32>>;
+
+/// The smoothing filter of the effects, which follows the smoothing method of the settings
+struct SmoothingFilter {
+    method: SmoothingMethod,
+    filter: Box<dyn Smoothing<[f32]>>,
+}
+
+impl SmoothingFilter {
+    /// Create the filter of the default smoothing method
+    fn smoothing_settings() -> Self {
+        let method = SmoothingMethod::default();
+        SmoothingFilter { method, filter: method.create() }
+    }
+}
 
 pub struct AudioData<'a> {
     pub(crate) melbank: &'a[f32],
+    /// The melbank of every single channel. Empty, if the channels were only analyzed as mono downmix
+    pub(crate) channel_melbanks: &'a [Vec<f32>],
+    /// The constant-Q transform of the mono signal with note aligned bins.
+    /// Only its harmonic part, if the effect requires the separation. Empty, if neither the settings nor the effect require it
+    pub(crate) constant_q: &'a [f32],
+    /// The energy of every pitch class, beginning with C. The largest bin is 1
+    pub(crate) chroma: [f32; 12],
+    /// The smoothed key estimate. None, if there was no tonal content yet
+    pub(crate) key: Option<Key>,
+    /// The onset strength and if a new onset started in this frame
+    pub(crate) onset: Onset,
+    /// The tempo, the beat phase and the beat events of the beat tracker
+    pub(crate) beat: Beat,
+    /// The kick, snare and hi-hat hits
+    pub(crate) percussion: Percussion,
+    /// The melbank of the harmonic part of the mono signal. Empty, if the effect doesn't require the separation
+    pub(crate) harmonic_melbank: &'a [f32],
+    /// The melbank of the percussive part of the mono signal. Empty, if the effect doesn't require the separation
+    pub(crate) percussive_melbank: &'a [f32],
     pub(crate) power_spectrum: &'a [f32],
+    /// The frequency of every bin of the power spectrum in Hz
+    pub(crate) frequencies: &'a [f32],
     pub(crate) raw_data: &'a [f32],
     pub(crate) settings: Settings,
     pub(crate) sample_rate: u32,
+    /// The time between two frames in seconds
+    pub(crate) frame_interval: f32,
     pub color: [u8; 3],
 }
 
+impl AudioData<'_> {
+
+    /// Get the melbank of the left channel.
+    /// If the channels were not analyzed separately, the mono melbank is returned.
+    pub fn left_melbank(&self) -> &[f32] {
+        self.channel_melbanks.first()
+            .map_or(self.melbank, |it| it.as_slice())
+    }
+
+    /// Get the melbank of the right channel.
+    /// For a mono input the left channel, and without separate analysis the mono melbank is returned.
+    pub fn right_melbank(&self) -> &[f32] {
+        self.channel_melbanks.get(1)
+            .map_or(self.left_melbank(), |it| it.as_slice())
+    }
+}
 
 /// Color Object to paint effects and proceed color changes.
 pub struct Color {
     /// The current color
     color: [u8; 3],
-    transition_time: u8,
-    /// The current amount of frames which are needed to finish a color change
-    transition_step: Option<u8>,
-    /// The transition color values, which will be added to the current color to proceed a color change
-    transition_color: Option<[i16; 3]>,
+    /// The time in milliseconds, which a color change needs to take
+    transition_time: f32,
+    /// The start and the target color of the running color change
+    transition: Option<([u8; 3], [u8; 3])>,
+    /// The time in milliseconds since the running color change started
+    elapsed: f32,
 }
 
 impl Color {
 
-    /// The default time how many frames a color change needs to take.
-    const DEFAULT_TRANSITION_TIME: u8 = 20;
+    /// The default time in milliseconds, which a color change needs to take.
+    const DEFAULT_TRANSITION_TIME: f32 = 200.0;
 
     /// Create a new Color-object in the RGB-Format
     pub fn new(rgb: [u8; 3]) -> Self {
         Color {
             color: rgb,
             transition_time: Self::DEFAULT_TRANSITION_TIME,
-            transition_step: None,
-            transition_color: None,
+            transition: None,
+            elapsed: 0.0,
         }
     }
 
-    /// Get the current color in the RGB-Format
-    pub fn rgb(&mut self) ->[u8; 3] {
+    /// Get the current color in the RGB-Format.
+    /// The frame interval is the time since the last frame in seconds, which proceeds a running color change
+    pub fn rgb(&mut self, frame_interval: f32) -> [u8; 3] {
         // If there is any transition going on
-        if let Some(transition_step) = self.transition_step {
-            // Show if the transition reached the end and reset
-            if transition_step == 0 {
-                self.transition_step = None;
-                self.transition_color = None;
-            }
-            // Update the transition
-            if let Some(transition_color) = self.transition_color {
-                let r = self.color[0] as i16 + transition_color[0];
-                let g = self.color[1] as i16 + transition_color[1];
-                let b = self.color[2] as i16 + transition_color[2];
-
-                self.color = [r as u8, g as u8, b as u8];
-                // Update step
-                self.transition_step = Some(transition_step-1);
+        if let Some((start, target)) = self.transition {
+            self.elapsed += frame_interval * 1000.0;
+            let progress = if self.transition_time > 0.0 { (self.elapsed / self.transition_time).min(1.0) } else { 1.0 };
+
+            // Interpolate between the start and the target color
+            for ((color, start), target) in self.color.iter_mut().zip(start).zip(target) {
+                *color = (start as f32 + (target as f32 - start as f32) * progress).round() as u8;
             }
 
+            // Reset, if the transition reached the end
+            if progress >= 1.0 {
+                self.transition = None;
+            }
         }
 
         self.color
@@ -87,15 +151,12 @@ impl Color {
 
     /// Do a color change
     pub fn change_color(&mut self, rgb: [u8; 3]) {
-        let step_r = (rgb[0] as i16 - self.color[0] as i16) / self.transition_time as i16;
-        let step_g = rgb[1] as i16 - self.color[1] as i16 / self.transition_time as i16;
-        let step_b = rgb[2] as i16 - self.color[2] as i16 / self.transition_time as i16;
-
-        self.transition_step = Some(self.transition_time);
-        self.transition_color = Some([step_r, step_g, step_b]);
+        self.transition = Some((self.color, rgb));
+        self.elapsed = 0.0;
     }
 
-    pub fn change_transition_time(&mut self, transition_time: u8) {
+    /// Change the time in milliseconds, which a color change needs to take
+    pub fn change_transition_time(&mut self, transition_time: f32) {
         self.transition_time = transition_time;
     }
 
@@ -124,6 +185,15 @@ pub trait AudioEffect: Send + 'static {
 
     fn disable_color_wheel(&self) -> bool { false }
 
+    /// If the effect needs the melbank of every single channel, the channels will always be analyzed separately.
+    fn requires_channel_melbanks(&self) -> bool { false }
+
+    /// If the effect needs the constant-Q transform, it will be calculated alongside the melbank.
+    fn requires_constant_q(&self) -> bool { false }
+
+    /// If the effect needs the harmonic and percussive melbanks, the separation will be calculated.
+    fn requires_hpss(&self) -> bool { false }
+
 }
 
 pub struct EffectDescription {
@@ -132,20 +202,25 @@ pub struct EffectDescription {
 }
 
 
-fn apply_gain_filter(buffer: &mut [f32], filter: &mut GainFilter) {
+fn apply_gain_filter(buffer: &mut [f32], filter: &mut GainFilter, frame_interval: f32) {
     // Apply the gain filter
     if let Some(max) = buffer.iter().max_by(|x, y| x.partial_cmp(y).unwrap()) {
         // Get the max of the frame and update the gain filter
-        let max = filter.update(*max);
+        let max = filter.update(*max, frame_interval);
         // Then apply the gain for every value in the frame
         buffer.iter_mut()
             .for_each(|val| *val /= max);
     }
 }
 
-fn apply_smoothing_filter(buffer: &mut [f32], filter: &mut SmoothingFilter) {
+fn apply_smoothing_filter(buffer: &mut [f32], filter: &mut SmoothingFilter, method: SmoothingMethod, frame_interval: f32) {
+    // Start a new filter, if another smoothing method was selected
+    if filter.method != method {
+        *filter = SmoothingFilter { method, filter: method.create() };
+    }
+
     // Apply the smoothing filter
-    filter.update(buffer);
+    filter.filter.smooth(buffer, frame_interval);
 }
 
 #[macro_export]
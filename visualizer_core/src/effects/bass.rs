@@ -1,21 +1,24 @@
 use super::*;
-use crate::dsp::{PeakDetector, apply_mel_matrix};
+use crate::dsp::{MelbankCache, PeakDetector};
 use crate::math::gaussian_curve;
 
-const ACCURACY: f32 = 0.1;
+// All times are in milliseconds
+const ACCURACY: f32 = 100.0;
 const SENSITIVITY: f32 = 1.5;
-const GAIN_DECAY: f32 = 0.001;
-const SMOOTHING: (f32, f32) = (0.6, 0.05);
+const GAIN_DECAY: f32 = 10_000.0;
+const SMOOTHING: (f32, f32) = (12.0, 200.0);
 
 pub struct BassEffect {
-    peak_detector: PeakDetector
+    peak_detector: PeakDetector,
+    melbank: MelbankCache,
 }
 
 impl BassEffect {
 
     pub fn new() -> Self {
         BassEffect {
-            peak_detector: PeakDetector::new(ACCURACY, SENSITIVITY, GAIN_DECAY, SMOOTHING)
+            peak_detector: PeakDetector::new(ACCURACY, SENSITIVITY, GAIN_DECAY, SMOOTHING),
+            melbank: MelbankCache::new(),
         }
     }
 
@@ -25,8 +28,9 @@ impl BassEffect {
 impl AudioEffect for BassEffect {
     fn visualize(&mut self, data: AudioData) -> Vec<f32> {
         let size = data.melbank.len();
-        let melbank = apply_mel_matrix(data.power_spectrum, 0.0, 200.0, size, data.sample_rate);
-        let (output, _) = self.peak_detector.update(melbank.as_slice());
+        let mut melbank = self.melbank.apply(data.power_spectrum, 0.0, 200.0, size, data.sample_rate);
+        data.settings.equalizer.apply(&mut melbank, self.melbank.center_frequencies());
+        let (output, _) = self.peak_detector.update(melbank.as_slice(), data.frame_interval);
 
         let mut gaussian = gaussian_curve(size, 10.0);
         // Apply the output to the gaussian curve
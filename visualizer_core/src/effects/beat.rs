@@ -0,0 +1,55 @@
+use super::*;
+use crate::math::{hsv_to_rgb, transpose};
+use crate::stream::channel::{Frame, ViewFrame};
+
+/// The hue change on every downbeat in degrees
+const HUE_STEP: f32 = 90.0;
+
+/// Pulses with the beat clock and changes the color on every downbeat
+pub struct BeatEffect {
+    hue: f32,
+}
+
+impl BeatEffect {
+    pub fn new() -> BeatEffect {
+        BeatEffect {
+            hue: 0.0,
+        }
+    }
+}
+
+impl AudioEffect for BeatEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        // Without a tempo there is no beat to show
+        if data.beat.bpm <= 0.0 {
+            return vec![0.0; data.settings.n_bins];
+        }
+
+        // The pulse is brightest on the beat and fades until the next beat
+        let value = (1.0 - data.beat.phase).powi(2);
+        vec![value; data.settings.n_bins]
+    }
+
+    fn transpose_animation(&mut self, data: AudioData) -> Frame {
+        if data.beat.downbeat {
+            self.hue = (self.hue + HUE_STEP) % 360.0;
+        }
+        let animation = self.visualize(data);
+
+        let color = hsv_to_rgb(self.hue, 1.0, 1.0);
+        let transposed = transpose(animation.as_slice(), color);
+
+        Frame {
+            data: Some(transposed),
+            view: Some(ViewFrame {
+                effect: animation,
+                color,
+            }),
+        }
+    }
+
+    fn disable_color_wheel(&self) -> bool {
+        true
+    }
+}
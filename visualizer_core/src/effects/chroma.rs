@@ -0,0 +1,75 @@
+use super::*;
+use crate::dsp::Mode;
+use crate::math::{hsv_to_rgb, transpose};
+use crate::stream::channel::{Frame, ViewFrame};
+
+/// Below this confidence, the key is ignored and the selected color is used
+const MIN_CONFIDENCE: f32 = 0.5;
+
+/// Shows the energy of the 12 pitch classes in the color of the estimated key.
+/// The hue follows the circle of fifths, so related keys get similar colors. Minor keys are darker.
+pub struct ChromaEffect {
+    smooth_filter: SmoothingFilter,
+    color: Color,
+    key: Option<(usize, Mode)>,
+}
+
+impl ChromaEffect {
+    pub fn new() -> ChromaEffect {
+        ChromaEffect {
+            smooth_filter: SmoothingFilter::smoothing_settings(),
+            color: Color::new([255; 3]),
+            key: None,
+        }
+    }
+
+    /// Change the color, if the estimated key changed
+    fn update_color(&mut self, key: Option<Key>, default_color: [u8; 3]) {
+        let key = key.filter(|it| it.confidence >= MIN_CONFIDENCE);
+        let current = key.map(|it| (it.tonic, it.mode));
+        if current == self.key { return; }
+        self.key = current;
+
+        let color = match key {
+            Some(key) => {
+                let hue = ((key.tonic * 7) % 12) as f32 * 30.0;
+                let value = if key.mode == Mode::Major { 1.0 } else { 0.6 };
+                hsv_to_rgb(hue, 1.0, value)
+            }
+            None => default_color,
+        };
+        self.color.change_color(color);
+    }
+}
+
+impl AudioEffect for ChromaEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        // Stretch the pitch classes over all bins
+        let n_bins = data.settings.n_bins;
+        let mut buffer = (0..n_bins)
+            .map(|i| data.chroma[i * 12 / n_bins.max(1)])
+            .collect::<Vec<f32>>();
+
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, data.settings.smoothing, data.frame_interval);
+
+        buffer
+    }
+
+    fn transpose_animation(&mut self, data: AudioData) -> Frame {
+        self.update_color(data.key, data.color);
+        let frame_interval = data.frame_interval;
+        let animation = self.visualize(data);
+
+        let color = self.color.rgb(frame_interval);
+        let transposed = transpose(animation.as_slice(), color);
+
+        Frame {
+            data: Some(transposed),
+            view: Some(ViewFrame {
+                effect: animation,
+                color,
+            }),
+        }
+    }
+}
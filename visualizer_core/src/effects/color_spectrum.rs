@@ -22,8 +22,8 @@ impl ColorSpectrumEffect {
 
     pub fn animate_color_spectrum(&mut self, data: AudioData) -> Vec<u8> {
         let mut buffer = data.melbank.to_vec();
-        apply_gain_filter(&mut buffer, &mut self.gain_filter);
-        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter);
+        apply_gain_filter(&mut buffer, &mut self.gain_filter, data.frame_interval);
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, data.settings.smoothing, data.frame_interval);
 
         let chunk_len = buffer.len() / 3;
         let c: Vec<&[f32]> = (0..3)
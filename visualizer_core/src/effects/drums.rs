@@ -0,0 +1,71 @@
+use super::*;
+use crate::math::transpose;
+use crate::stream::channel::{Frame, ViewFrame};
+
+const KICK_COLOR: [u8; 3] = [255, 0, 0];
+const SNARE_COLOR: [u8; 3] = [255, 255, 255];
+const HIHAT_COLOR: [u8; 3] = [0, 128, 255];
+
+/// Every drum gets its own light behaviour:
+/// The kick pulses from the center, the snare flashes the whole strip and the hi-hat sparkles on the outer ends
+pub struct DrumsEffect;
+
+impl DrumsEffect {
+    pub fn new() -> DrumsEffect {
+        DrumsEffect
+    }
+
+    /// Get the brightness of every drum for every bin
+    fn layers(data: &AudioData) -> [Vec<f32>; 3] {
+        let n_bins = data.settings.n_bins;
+        let center = n_bins as f32 / 2.0;
+        let percussion = data.percussion;
+
+        // The kick grows from the center with its strength
+        let kick = (0..n_bins)
+            .map(|i| if ((i as f32 + 0.5) - center).abs() < center * percussion.kick.strength { percussion.kick.strength } else { 0.0 })
+            .collect();
+        let snare = vec![percussion.snare.strength; n_bins];
+        // The hi-hat only lights the outer quarters
+        let hihat = (0..n_bins)
+            .map(|i| if ((i as f32 + 0.5) - center).abs() > center / 2.0 { percussion.hihat.strength } else { 0.0 })
+            .collect();
+
+        [kick, snare, hihat]
+    }
+}
+
+impl AudioEffect for DrumsEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        let [kick, snare, hihat] = Self::layers(&data);
+
+        kick.iter().zip(snare.iter()).zip(hihat.iter())
+            .map(|((a, b), c)| a.max(*b).max(*c))
+            .collect()
+    }
+
+    fn transpose_animation(&mut self, data: AudioData) -> Frame {
+        let [kick, snare, hihat] = Self::layers(&data);
+
+        // Take the brightest channel of all drums
+        let mut transposed = transpose(&kick, KICK_COLOR);
+        for (layer, color) in [(snare, SNARE_COLOR), (hihat, HIHAT_COLOR)] {
+            for (value, other) in transposed.iter_mut().zip(transpose(&layer, color)) {
+                *value = (*value).max(other);
+            }
+        }
+
+        Frame {
+            data: Some(transposed),
+            view: Some(ViewFrame {
+                effect: self.visualize(data),
+                color: KICK_COLOR,
+            }),
+        }
+    }
+
+    fn disable_color_wheel(&self) -> bool {
+        true
+    }
+}
@@ -5,10 +5,11 @@ use crate::math::gaussian_curve;
 use num_traits::Pow;
 
 
-const GAIN_RISE: f32 = 0.9;
-const GAIN_DECAY: f32 = 0.001;
-const SMOOTHING_RISE: f32 = 0.4;
-const SMOOTHING_DECAY: f32 = 0.1;
+// All times are in milliseconds
+const GAIN_RISE: f32 = 5.0;
+const GAIN_DECAY: f32 = 10_000.0;
+const SMOOTHING_RISE: f32 = 20.0;
+const SMOOTHING_DECAY: f32 = 100.0;
 const STANDARD_DEVIATION: f32 = 10.0;
 
 pub struct EnergyEffect {
@@ -33,9 +34,9 @@ impl EnergyEffect {
             .sum::<f32>();
 
         let rms = (energy / size as f32).sqrt();
-        let rms = rms / self.gain_filter.update(rms);
+        let rms = rms / self.gain_filter.update(rms, data.frame_interval);
 
-        self.smoothing_filter.update(rms)
+        self.smoothing_filter.update(rms, data.frame_interval)
     }
     
     
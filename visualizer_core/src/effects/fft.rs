@@ -17,8 +17,13 @@ impl AudioEffect for FftEffect {
 
     fn visualize(&mut self, data: AudioData) -> Vec<f32> {
 
-        let mut buffer = data.power_spectrum.to_vec();
-        apply_gain_filter(&mut buffer, &mut self.gain_filter);
+        // Only show the bins inside the frequency range of the settings
+        let (min, max) = (data.settings.min_frequency as f32, data.settings.max_frequency as f32);
+        let mut buffer = data.power_spectrum.iter().zip(data.frequencies)
+            .filter(|(_, frequency)| (min..=max).contains(*frequency))
+            .map(|(power, _)| *power)
+            .collect::<Vec<f32>>();
+        apply_gain_filter(&mut buffer, &mut self.gain_filter, data.frame_interval);
 
         buffer
     }
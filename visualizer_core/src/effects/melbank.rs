@@ -19,11 +19,16 @@ impl MelbankEffect {
 impl AudioEffect for MelbankEffect {
 
     fn visualize(&mut self, data: AudioData) -> Vec<f32> {
-        let mut buffer = data.melbank.to_vec();
+        // Use the harmonic part, so drum hits don't let the spectrum flicker
+        let mut buffer = data.harmonic_melbank.to_vec();
 
-        apply_gain_filter(&mut buffer, &mut self.gain_filter);
-        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter);
+        apply_gain_filter(&mut buffer, &mut self.gain_filter, data.frame_interval);
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, data.settings.smoothing, data.frame_interval);
 
         buffer
     }
+
+    fn requires_hpss(&self) -> bool {
+        true
+    }
 }
\ No newline at end of file
@@ -0,0 +1,38 @@
+use super::*;
+
+/// Shows the constant-Q transform, where every bin is a single note
+pub struct NoteSpectrumEffect {
+    gain_filter: GainFilter,
+    smooth_filter: SmoothingFilter,
+}
+
+impl NoteSpectrumEffect {
+    pub fn new() -> NoteSpectrumEffect {
+        NoteSpectrumEffect {
+            gain_filter: GainFilter::gain_settings(),
+            smooth_filter: SmoothingFilter::smoothing_settings(),
+        }
+    }
+}
+
+impl AudioEffect for NoteSpectrumEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        // The constant-Q transform only contains the harmonic part, so drum hits don't let the notes flicker
+        let mut buffer = data.constant_q.to_vec();
+
+        apply_gain_filter(&mut buffer, &mut self.gain_filter, data.frame_interval);
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, data.settings.smoothing, data.frame_interval);
+
+        buffer
+    }
+
+    fn requires_constant_q(&self) -> bool {
+        true
+    }
+
+    fn requires_hpss(&self) -> bool {
+        true
+    }
+
+}
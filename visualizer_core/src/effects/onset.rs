@@ -0,0 +1,31 @@
+use super::*;
+
+/// The release time of the brightness in milliseconds
+const DECAY: f32 = 65.0;
+
+/// Flashes all bins on every onset and follows the onset strength in between
+pub struct OnsetEffect {
+    brightness: f32,
+}
+
+impl OnsetEffect {
+    pub fn new() -> OnsetEffect {
+        OnsetEffect {
+            brightness: 0.0,
+        }
+    }
+}
+
+impl AudioEffect for OnsetEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        self.brightness *= (-data.frame_interval * 1000.0 / DECAY).exp();
+        if data.onset.detected {
+            self.brightness = 1.0;
+        }
+
+        // The onset strength is shown as a dim glow
+        let value = self.brightness.max(data.onset.strength * 0.3);
+        vec![value; data.settings.n_bins]
+    }
+}
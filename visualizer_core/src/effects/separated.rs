@@ -0,0 +1,44 @@
+use super::*;
+use crate::math::Flip;
+
+/// Shows the harmonic part of the signal on the left half and the percussive part on the right half
+pub struct SeparatedSpectrumEffect {
+    harmonic_gain: GainFilter,
+    percussive_gain: GainFilter,
+    smooth_filter: SmoothingFilter,
+}
+
+impl SeparatedSpectrumEffect {
+    pub fn new() -> SeparatedSpectrumEffect {
+        SeparatedSpectrumEffect {
+            harmonic_gain: GainFilter::gain_settings(),
+            percussive_gain: GainFilter::gain_settings(),
+            smooth_filter: SmoothingFilter::smoothing_settings(),
+        }
+    }
+}
+
+impl AudioEffect for SeparatedSpectrumEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        // Both parts have their own gain, because the percussive part is usually much quieter
+        let mut harmonic = data.harmonic_melbank.to_vec();
+        let mut percussive = data.percussive_melbank.to_vec();
+        apply_gain_filter(&mut harmonic, &mut self.harmonic_gain, data.frame_interval);
+        apply_gain_filter(&mut percussive, &mut self.percussive_gain, data.frame_interval);
+
+        let mut buffer = [harmonic.clone_flip(), percussive].concat();
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, data.settings.smoothing, data.frame_interval);
+
+        buffer
+    }
+
+    fn amount_melbank_bins(&self, amount_led_bins: usize) -> usize {
+        amount_led_bins/2
+    }
+
+    fn requires_hpss(&self) -> bool {
+        true
+    }
+
+}
@@ -1,5 +1,5 @@
 use super::*;
-use crate::dsp::{PeakDetector, apply_mel_matrix};
+use crate::dsp::{MelbankCache, PeakDetector};
 use crate::math::{transpose, Flip};
 use crate::stream::channel::{Frame, ViewFrame};
 
@@ -7,20 +7,23 @@ pub struct ShineEffect {
     gain_filter: GainFilter,
     smooth_filter: SmoothingFilter,
     peak_detector: PeakDetector,
+    melbank: MelbankCache,
     color: Color
 }
 
 const SHINE_FREQ: (f32, f32) = (0.0, 200.0);
-const SHINE_SMOOTHING: (f32, f32) = (0.8, 0.1);
+/// The attack and release time of the shine in milliseconds
+const SHINE_SMOOTHING: (f32, f32) = (7.0, 100.0);
 const SHINE_COLOR: [u8; 3] = [255; 3];
-const TRANSITION_TIME: u8 = 3;
+/// The time of a color change in milliseconds
+const TRANSITION_TIME: f32 = 30.0;
 
 impl ShineEffect {
     pub fn new() -> ShineEffect {
         let detector =  PeakDetector::new(
-            0.1,
+            100.0,
             1.5,
-            0.0001,
+            100_000.0,
             (SHINE_SMOOTHING.0, SHINE_SMOOTHING.1),
         );
         let color = Color::new(SHINE_COLOR);
@@ -29,6 +32,7 @@ impl ShineEffect {
             gain_filter: GainFilter::gain_settings(),
             smooth_filter: SmoothingFilter::smoothing_settings(),
             peak_detector: detector,
+            melbank: MelbankCache::new(),
             color,
         }
     }
@@ -36,24 +40,25 @@ impl ShineEffect {
 
 impl ShineEffect {
 
-    fn build_spectrum_animation(&mut self, melbank: &[f32]) -> Vec<f32> {
+    fn build_spectrum_animation(&mut self, melbank: &[f32], smoothing: SmoothingMethod, frame_interval: f32) -> Vec<f32> {
         let mut buffer = melbank.to_vec();
 
-        apply_gain_filter(&mut buffer, &mut self.gain_filter);
-        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter);
+        apply_gain_filter(&mut buffer, &mut self.gain_filter, frame_interval);
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, smoothing, frame_interval);
 
         // Reflect the signal in the middle
         [buffer.clone_flip(), buffer].concat()
     }
 
     fn build_shine_animation(&mut self, data: &AudioData) -> Vec<f32> {
-        let melbank = apply_mel_matrix(data.power_spectrum, SHINE_FREQ.0, SHINE_FREQ.1, 60, data.sample_rate);
+        let mut melbank = self.melbank.apply(data.power_spectrum, SHINE_FREQ.0, SHINE_FREQ.1, 60, data.sample_rate);
+        data.settings.equalizer.apply(&mut melbank, self.melbank.center_frequencies());
 
-        let (peak_value, peak_update) = self.peak_detector.update(melbank.as_slice());
+        let (peak_value, peak_update) = self.peak_detector.update(melbank.as_slice(), data.frame_interval);
 
         let mut out = vec![1.0f32; data.settings.n_bins];
         for x in out.iter_mut() {
-            *x = *x * peak_value;
+            *x *= peak_value;
         }
 
         // Update if a peak started, or ended
@@ -66,7 +71,7 @@ impl ShineEffect {
 
     fn peak_changed(&mut self, default_color: [u8; 3],  started: bool) {
         let color = if started { SHINE_COLOR } else { default_color };
-        let time = if started { TRANSITION_TIME*2 } else { TRANSITION_TIME };
+        let time = if started { TRANSITION_TIME*2.0 } else { TRANSITION_TIME };
 
         self.color.change_color(color);
         self.color.change_transition_time(time)
@@ -80,7 +85,7 @@ impl AudioEffect for ShineEffect {
 
     fn visualize(&mut self, data: AudioData) -> Vec<f32> {
 
-        let mut main_animation = self.build_spectrum_animation(data.melbank);
+        let mut main_animation = self.build_spectrum_animation(data.melbank, data.settings.smoothing, data.frame_interval);
         let shine_animation = self.build_shine_animation(&data);
 
         // Take the stronger animation
@@ -94,10 +99,11 @@ impl AudioEffect for ShineEffect {
     }
 
     fn transpose_animation(&mut self, data: AudioData) -> Frame {
+        let frame_interval = data.frame_interval;
         let animation = self.visualize(data);
 
         // Update the color
-        let color = self.color.rgb();
+        let color = self.color.rgb(frame_interval);
         // Transpose the signal with the color
         let transposed = transpose(animation.as_slice(), color);
 
@@ -0,0 +1,40 @@
+use super::*;
+use crate::math::Flip;
+
+pub struct StereoSpectrumEffect {
+    gain_filter: GainFilter,
+    smooth_filter: SmoothingFilter,
+}
+
+impl StereoSpectrumEffect {
+    pub fn new() -> StereoSpectrumEffect {
+        StereoSpectrumEffect {
+            gain_filter: GainFilter::gain_settings(),
+            smooth_filter: SmoothingFilter::smoothing_settings(),
+        }
+    }
+}
+
+impl AudioEffect for StereoSpectrumEffect {
+
+    fn visualize(&mut self, data: AudioData) -> Vec<f32> {
+        // Show the left channel on the left half and the right channel on the right half
+        let left = data.left_melbank().to_vec();
+        let mut buffer = [left.clone_flip(), data.right_melbank().to_vec()].concat();
+
+        // Both channels share the same gain to keep the balance between them
+        apply_gain_filter(&mut buffer, &mut self.gain_filter, data.frame_interval);
+        apply_smoothing_filter(&mut buffer, &mut self.smooth_filter, data.settings.smoothing, data.frame_interval);
+
+        buffer
+    }
+
+    fn amount_melbank_bins(&self, amount_led_bins: usize) -> usize {
+        amount_led_bins/2
+    }
+
+    fn requires_channel_melbanks(&self) -> bool {
+        true
+    }
+
+}
@@ -1,13 +1,15 @@
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use log::info;
 
 use thiserror::Error;
 use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::{SampleFormat, SupportedStreamConfig};
 
 use sender::SacnSender;
 use stream::Stream;
 use stream::channel::{Receiver, ViewFrame};
+use stream::source::{AudioSource, DeviceSource};
 use effects::*;
 
 /// All digital signal processing related stuff
@@ -25,9 +27,11 @@ mod sender;
 mod test;
 
 // Export all needed utilities
-pub use cpal::HostId;
-pub use stream::Settings;
+pub use cpal::{HostId, SampleFormat};
+pub use stream::{ChannelMode, Filterbank, Settings, StreamConfigRequest, StreamInfo};
 pub use stream::channel::ViewFrame as StreamFrame;
+pub use stream::source::{FileSource, Pacing};
+pub use dsp::{AgcSettings, EqPoint, FilterNormalization, FrequencyScale, GraphicEq, LoudnessWeighting, NoiseProfile, NoiseSettings, SmoothingMethod, WindowFunction};
 use crate::ControllerError::NoValidEffectName;
 
 // Help to declare all method results with the ControllerError Type
@@ -38,8 +42,11 @@ pub struct Controller {
     host: Option<cpal::Host>,
     device: Option<cpal::Device>,
     stream_handler: Stream,
+    config_request: StreamConfigRequest,
     sender: SacnSender,
-    effects: Vec<EffectDescription>
+    effects: Vec<EffectDescription>,
+    /// The directory, where the noise profile of every input device is saved
+    noise_profile_directory: PathBuf,
 }
 
 /// All errors that can occur during the program's runtime
@@ -58,7 +65,11 @@ pub enum  ControllerError {
     #[error("The given Effect ID is not available")]
     NoValidEffectName,
     #[error("No Stream created yet")]
-    NoStream
+    NoStream,
+    #[error("Failure while reading the audio file")]
+    AudioFileError(Box<dyn Error>),
+    #[error("The audio file format is not supported")]
+    UnsupportedFileFormat
 }
 
 /// The audio input device used by the program to receive audio signals.
@@ -70,14 +81,29 @@ pub struct InputDevice {
 }
 
 
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Controller {
 
+    /// The directory of the noise profiles, relative to the working directory
+    const DEFAULT_NOISE_PROFILE_DIRECTORY: &'static str = "noise_profiles";
+
     /// Create a new controller
     pub fn new() -> Controller {
         let effects: Vec<EffectDescription> = register_effects! {
             "Melbank" => MelbankEffect::new,
             "Spectrum" => SpectrumEffect::new,
+            "Stereo Spectrum" => StereoSpectrumEffect::new,
+            "Note Spectrum" => NoteSpectrumEffect::new,
+            "Chroma" => ChromaEffect::new,
+            "Onsets" => OnsetEffect::new,
+            "Beat" => BeatEffect::new,
+            "Drums" => DrumsEffect::new,
+            "Separated Spectrum" => SeparatedSpectrumEffect::new,
             "Shine" => ShineEffect::new,
             "Energy" => EnergyEffect::new,
             "Bass" => BassEffect::new,
@@ -89,8 +115,10 @@ impl Controller {
             host: None,
             device: None,
             stream_handler: Stream::new(),
+            config_request: StreamConfigRequest::default(),
             sender: SacnSender::new_multicast_sender(),
-            effects
+            effects,
+            noise_profile_directory: PathBuf::from(Self::DEFAULT_NOISE_PROFILE_DIRECTORY),
         }
     }
 
@@ -144,7 +172,7 @@ impl Controller {
             .input_devices()
             .map_err(|e| ControllerError::CPALError(e.into()))?;
 
-        let selected = devices.skip(id).next()
+        let selected = devices.into_iter().nth(id)
             .expect("");
         info!("Select device: {:?}", selected.name());
 
@@ -159,6 +187,13 @@ impl Controller {
         self.open_stream(effect, settings, color)
     }
 
+    /// Change the preferred sample rate, buffer size and channel count of the input device.
+    /// The request is used the next time a stream is opened on a device.
+    pub fn change_stream_config(&mut self, request: StreamConfigRequest) {
+        info!("Request stream config {:?}", request);
+        self.config_request = request;
+    }
+
     /// Update the audio settings of the stream
     pub fn update_stream_settings(&mut self, settings: Settings) {
         self.stream_handler.update_settings(settings)
@@ -187,23 +222,81 @@ impl Controller {
         self.stream_handler.is_color_selection_used()
     }
 
+    /// Learn the noise of the input for the duration, e.g. the HVAC or the crowd of a room microphone.
+    /// The average noise spectrum is subtracted from the input afterward and saved for the input device
+    pub fn learn_noise(&mut self, duration: Duration) -> Result<()> {
+        self.stream_handler.learn_noise(duration.as_secs_f32())
+    }
+
+    /// Remove the noise profile of the current input device
+    pub fn clear_noise_profile(&mut self) {
+        self.stream_handler.clear_noise_profile()
+    }
+
+    /// True, while the noise of the input is learned
+    pub fn is_learning_noise(&self) -> bool {
+        self.stream_handler.is_learning_noise()
+    }
+
+    /// True, if a noise profile is subtracted from the input
+    pub fn has_noise_profile(&self) -> bool {
+        self.stream_handler.has_noise_profile()
+    }
+
+    /// Change the directory, where the noise profile of every input device is saved.
+    /// The change is used the next time a stream is opened on a device.
+    pub fn change_noise_profile_directory<P: AsRef<Path>>(&mut self, path: P) {
+        self.noise_profile_directory = path.as_ref().to_path_buf();
+    }
+
+    /// Open a stream which plays an audio file (WAV or FLAC) instead of a live input device.
+    /// The pacing defines if the file is played in real time or as fast as possible.
+    pub fn open_file<P: AsRef<Path>>(&mut self, path: P, pacing: Pacing, effect: &'static str, settings: Settings, color: [u8; 3]) -> Result<std::sync::mpsc::Receiver<ViewFrame>> {
+        info!("Opening file stream");
+
+        // Audio files have no noise profile, which could be saved
+        let source = FileSource::open(path, pacing)?;
+        self.open_source(Box::new(source), effect, settings, color, None)
+    }
+
+    /// Get the effective properties of the current stream, like the sample format chosen for the device
+    pub fn stream_info(&self) -> Result<StreamInfo> {
+        self.stream_handler.info()
+    }
+
+    /// If the audio source of the stream has no more samples to deliver, this value will be true.
+    /// Live input devices never finish.
+    pub fn is_stream_finished(&self) -> bool {
+        self.stream_handler.is_finished()
+    }
+
     fn open_stream(&mut self, effect: &'static str, settings: Settings, color: [u8; 3]) -> Result<std::sync::mpsc::Receiver<ViewFrame>> {
         info!("Opening stream");
 
-        // Check if a valid device & config are available
+        // Check if a valid device is available and choose the closest config to the request
         let device = self.device.as_ref().ok_or(ControllerError::NoDeviceFound)?;
-        let configs = device.supported_input_configs()
-            .map_err(|e| ControllerError::CPALError(e.into()))?;
+        let source = DeviceSource::negotiate(device.clone(), self.config_request)?;
 
-        // Only use the config with the sample format f32
-        let mut config: Option<SupportedStreamConfig> = None;
-        for config_range in configs {
-            if config_range.sample_format() == SampleFormat::F32 {
-                config = Some(config_range.with_max_sample_rate());
-                break;
-            };
-        }
+        // Every device has its own noise profile, which is named after the host, the device and the stream config.
+        // Devices with the same name on different hosts or another config get their own profile
+        let host = self.host.as_ref().map_or("unknown", |it| it.id().name());
+        let noise_profile = device.name().ok()
+            .map(|name| format!("{}_{}_{}ch_{}hz", host, name, source.channels(), source.sample_rate()))
+            .map(|name| name.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect::<String>())
+            .map(|name| self.noise_profile_directory.join(format!("{}.noise", name)));
 
+        self.open_source(Box::new(source), effect, settings, color, noise_profile)
+    }
+
+    /// Process the samples of the audio source with the effect and send the result to the receivers
+    fn open_source(
+        &mut self,
+        source: Box<dyn AudioSource>,
+        effect: &'static str,
+        settings: Settings,
+        color: [u8; 3],
+        noise_profile: Option<PathBuf>
+    ) -> Result<std::sync::mpsc::Receiver<ViewFrame>> {
         // Get the effect
         let effect = self.effects.iter()
             .find(|it| it.name == effect)
@@ -212,21 +305,15 @@ impl Controller {
         // Build the effect
         let built = (effect.factory)();
 
-        // If a valid config with f32 was found create the stream
-        if let Some(config) = config {
-            // Start the stream and if an error occurs, notify the view
-            let rx = self.stream_handler.open(device, config.into(), settings,  color,  built)
-                .map_err(|e| ControllerError::CPALError(e.into()))?;
+        // Start the stream and if an error occurs, notify the view
+        let rx = self.stream_handler.open(source, settings, color, built, noise_profile)
+            .map_err(ControllerError::CPALError)?;
 
-            // Start the sacn sender
-            let Receiver { rx_sacn, rx_view } = rx;
-            self.sender.listen(rx_sacn);
+        // Start the sacn sender
+        let Receiver { rx_sacn, rx_view } = rx;
+        self.sender.listen(rx_sacn);
 
-            Ok(rx_view)
-        } else {
-            // if no valid config was found return error
-            Err(ControllerError::NoSupportedConfig)
-        }
+        Ok(rx_view)
     }
 
 }
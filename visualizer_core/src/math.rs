@@ -1,33 +1,18 @@
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum MathError {
-    #[error("Both arrays must have the same length")]
-    NoEqualLength
-}
-
-
-pub fn array_product(a: &[f32], b: &[f32]) -> Result<Vec<f32>, MathError> {
-    if a.len() != b.len() {
-        return Err(MathError::NoEqualLength)
-    }
-    let mut out = Vec::new();
-    for (num1, num2) in a.iter().zip(b.iter()) {
-        out.push(*num1 * *num2);
+/// Modified bessel function of the first kind with order zero
+pub fn bessel_i0(x: f32) -> f32 {
+    // Sum the power series until the terms don't change the result anymore
+    // I0(x) = sum((x/2)^(2k) / (k!)^2)
+    let mut sum = 1.0f32;
+    let mut term = 1.0f32;
+    let quarter_x2 = x * x / 4.0;
+
+    for k in 1..100 {
+        term *= quarter_x2 / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-8 { break; }
     }
 
-    Ok(out)
-}
-
-pub fn linspace(x0: f32, xend: f32, n: usize) -> Vec<f32> {
-    let mut out = vec![x0;n];
-
-    let delta = (xend - x0) / (n -1) as f32;
-    for i in 1..n {
-        out[i] = x0 + delta*i as f32;
-    }
-
-    out
+    sum
 }
 
 pub(crate) fn transpose(x: &[f32], color: [u8; 3]) -> Vec<u8> {
@@ -35,16 +20,35 @@ pub(crate) fn transpose(x: &[f32], color: [u8; 3]) -> Vec<u8> {
     out[0] = 0u8;
 
     let mut idx_output = 1;
-    for i in 1..x.len() {
-        out[idx_output] = (x[i] * color[0] as f32).round() as u8;
-        out[idx_output+1] = (x[i] * color[1] as f32).round() as u8;
-        out[idx_output+2] = (x[i] * color[2] as f32).round() as u8;
+    for value in x.iter().skip(1) {
+        out[idx_output] = (value * color[0] as f32).round() as u8;
+        out[idx_output+1] = (value * color[1] as f32).round() as u8;
+        out[idx_output+2] = (value * color[2] as f32).round() as u8;
         idx_output += 3;
     }
 
     out
 }
 
+/// Convert a color from hsv (hue in degrees, saturation and value from 0 to 1) to rgb
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let c = value * saturation;
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let m = value - c;
+    [r, g, b].map(|it| ((it + m) * 255.0).round() as u8)
+}
+
 pub fn gaussian_curve(len: usize, std: f32) -> Vec<f32> {
     let mut curve = Vec::with_capacity(len);
     let m = len as f32 - 1.0 ;
@@ -67,7 +71,7 @@ pub trait Flip {
 
 impl<T: Clone + Sized> Flip for Vec<T> {
     fn clone_flip(&self) -> Self {
-        Vec::from_iter(self.into_iter().cloned().rev())
+        Vec::from_iter(self.iter().cloned().rev())
     }
 }
 
@@ -7,13 +7,9 @@ use log::error;
 use sacn::packet::ACN_SDT_MULTICAST_PORT;
 use sacn::source::SacnSource;
 
-use super::ControllerError::SacnError;
-
 /// Default Universe the data is to be sent on
 const UNIVERSE: u16 = 1;
 
-type Result<T> = std::result::Result<T, crate::ControllerError>;
-
 pub struct SacnSender {
     source: Arc<Mutex<SacnSource>>,
     universe: u16,
@@ -48,41 +44,14 @@ impl SacnSender {
 
         // Spawn a new thread to send the data
         thread::spawn(move || {
-            loop {
-                if let Ok(data) = receiver.recv() {
-                    // Only send effects
-                    Self::send(&source, data.as_slice(), universe);
-                } else {
-                    break; // Break the loop if the channel disconnected
-                }
+            // The loop ends if the channel disconnected
+            while let Ok(data) = receiver.recv() {
+                // Only send effects
+                Self::send(&source, data.as_slice(), universe);
             }
         });
     }
 
-    /// Change the universe of the sender
-    pub fn set_universe(&mut self, universe: u16) -> Result<()> {
-        // Lock the source
-        let mut source = self.source.lock().expect("Thread failed while holding the lock");
-
-        // Register the new universe, if successful disconnect from old universe and update
-        match source.register_universe(universe) {
-            Ok(_) => {
-                match source.terminate_stream(self.universe, 0) {
-                    Ok(_) => {
-                        Ok(self.universe = universe)
-                    }
-                    Err(err) => { Err(SacnError(err)) }
-                }
-            }
-            Err(err) => { Err(SacnError(err)) }
-        }
-    }
-
-    /// Get the currently used universe
-    fn get_universe(&self) -> u16 {
-        self.universe
-    }
-
     fn send(source: &Arc<Mutex<SacnSource>>, data: &[u8], universe: u16, /*priority: Option<u8>, dst_ip: Option<SocketAddr>, sync_universe: Option<u16>*/)  {
         // Lock the source and send the data
         let mut source = source.lock().expect("Thread failed while holding the lock");
@@ -1,22 +1,72 @@
 use std::error::Error;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use log::error;
-
-use cpal::traits::{DeviceTrait, StreamTrait};
-use cpal::InputCallbackInfo;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+use std::thread;
+use std::time::Duration;
+use log::{info, warn};
 
 use channel::{Receiver, Sender};
+use queue::SampleConsumer;
+use source::AudioSource;
 use super::ControllerError;
-use super::dsp::tick;
+use super::dsp::{tick, AgcSettings, AnalysisContext, FilterNormalization, Framer, FrequencyScale, GraphicEq, LoudnessWeighting, NoiseReduction, SmoothingMethod, WindowFunction};
 use super::effects::AudioEffect;
 
 pub mod channel;
+pub mod queue;
+pub mod source;
 
-#[derive(Debug, Copy, Clone)]
+/// Defines how the channels of a multichannel input are analyzed
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// Mix all channels down to a single mono signal
+    #[default]
+    Downmix,
+    /// Analyze the mono downmix and additionally every channel on its own
+    PerChannel,
+}
+
+#[derive(Debug, Clone)]
 pub struct Settings {
     pub n_bins: usize,
     pub min_frequency: u16,
     pub max_frequency: u16,
+    pub channel_mode: ChannelMode,
+    /// The frequency scale, on which the bins of the melbank are spaced.
+    /// With the octave bands of the logarithmic scale, the amount of bins of the melbank is given by the bands between the minimum and the maximum frequency
+    pub frequency_scale: FrequencyScale,
+    pub filter_normalization: FilterNormalization,
+    pub filterbank: Filterbank,
+    /// The amount of bins per octave of the constant-Q transform
+    pub bins_per_octave: usize,
+    /// The amount of samples in every analysis frame
+    pub window_size: usize,
+    /// The amount of new samples between two analysis frames
+    pub hop_size: usize,
+    pub window_function: WindowFunction,
+    /// The length of the fft, which has to be a power of two.
+    /// Every frame is zero padded to this length. If the window is longer, the next power of two of the window size is used
+    pub fft_size: usize,
+    /// The window size of the multi-resolution analysis for the low frequencies.
+    /// None, if all frequencies are analyzed with the same window
+    pub bass_window_size: Option<usize>,
+    /// Below this frequency, the melbank is taken from the long bass window
+    pub crossover_frequency: u16,
+    /// The automatic gain control and the noise gate of the input
+    pub agc: AgcSettings,
+    /// The coefficient of the pre-emphasis filter, which boosts the high frequencies. 0 disables the filter
+    pub pre_emphasis: f32,
+    /// The weighting of the spectrum with the perceived loudness before the triangular filterbank.
+    /// With the constant-Q filterbank, every note bin is weighted with its center frequency.
+    /// The onset, beat, percussion, chroma and separation stages always use the unweighted spectrum
+    pub loudness_weighting: LoudnessWeighting,
+    /// The gain curve, which is applied on the bins of the filterbank before the effects.
+    /// The bass and shine effects apply it on their own bands as well
+    pub equalizer: GraphicEq,
+    /// The filter, which smooths the bins of the effects over time
+    pub smoothing: SmoothingMethod,
 }
 impl Default for Settings {
     fn default() -> Self {
@@ -24,81 +74,208 @@ impl Default for Settings {
             n_bins: 60,
             min_frequency: 20,
             max_frequency: 12000,
+            channel_mode: ChannelMode::default(),
+            frequency_scale: FrequencyScale::default(),
+            filter_normalization: FilterNormalization::default(),
+            filterbank: Filterbank::default(),
+            bins_per_octave: 12,
+            window_size: 1024,
+            hop_size: 512,
+            window_function: WindowFunction::default(),
+            fft_size: 2048,
+            bass_window_size: None,
+            crossover_frequency: 250,
+            agc: AgcSettings::default(),
+            pre_emphasis: 0.9,
+            loudness_weighting: LoudnessWeighting::default(),
+            equalizer: GraphicEq::default(),
+            smoothing: SmoothingMethod::default(),
         }
     }
 }
 
+/// Defines how the melbank of the effects is calculated
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Filterbank {
+    /// Triangular filters on the power spectrum, spaced on the frequency scale
+    #[default]
+    Triangular,
+    /// Constant-Q transform with note aligned bins, beginning at the minimum frequency
+    ConstantQ,
+}
+
+/// The preferred properties of an input device stream.
+/// Every value which is None will be chosen by the device.
+/// If the device doesn't support a value, the closest supported value is used instead.
+/// Audio files always use the properties of the file.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StreamConfigRequest {
+    pub sample_rate: Option<u32>,
+    /// The amount of frames per callback
+    pub buffer_size: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+/// The effective properties of an opened audio stream
+#[derive(Debug, Copy, Clone)]
+pub struct StreamInfo {
+    /// The sample format delivered by the source, before it was converted to f32
+    pub sample_format: cpal::SampleFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// The amount of frames per callback. None, if the device uses its default size
+    pub buffer_size: Option<u32>,
+}
+
 pub struct InnerStream {
-    pub last_frame: Vec<f32>,
+    /// The framer of every analyzed signal. The first one is always the mono downmix
+    pub framers: Vec<Framer>,
+    /// The framer with the long bass window of every analyzed signal. Empty, if the multi-resolution analysis is disabled
+    pub bass_framers: Vec<Framer>,
+    /// The framer with the sample history of the constant-Q transform of every signal, which needs the transform
+    pub constant_q_framers: Vec<Framer>,
     pub settings: Settings,
     pub sample_rate: u32,
-    pub sender: Sender,
+    pub channels: u16,
     pub color: [u8; 3],
     pub effect: Box<dyn AudioEffect>,
+    /// The noise profile of the input, which is subtracted from every spectrum
+    pub noise: NoiseReduction,
 }
 
 
 
 pub struct Stream {
-    cpal_stream: Option<cpal::Stream>,
+    source: Option<Box<dyn AudioSource>>,
     buffer: Option<Arc<Mutex<InnerStream>>>,
+    analysis_thread: Option<thread::JoinHandle<()>>,
+    running: Arc<AtomicBool>,
 }
 impl Stream {
 
+    /// The duration of audio, which the queue between the source and the analysis thread can hold
+    const QUEUE_DURATION: f32 = 0.5;
+
+    /// The time the analysis thread waits, if no new samples are available
+    const IDLE_TIME: Duration = Duration::from_millis(2);
+
     pub fn new() -> Self {
         Stream {
-            cpal_stream: None,
+            source: None,
             buffer: None,
+            analysis_thread: None,
+            running: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Start the audio source and process all of its samples with the given effect.
+    /// The noise profile of the source is loaded from the path and every newly learned profile is saved to it.
     pub fn open(
         &mut self,
-        device: &cpal::Device,
-        config: cpal::StreamConfig,
+        mut source: Box<dyn AudioSource>,
         settings: Settings,
         color: [u8; 3],
         effect: Box<dyn AudioEffect>,
+        noise_profile: Option<PathBuf>,
     ) -> Result<Receiver, Box<dyn Error>> {
+        info!("Open stream with {} channels at {} Hz ({})", source.channels(), source.sample_rate(), source.sample_format());
+        self.close();
+
         // Run the processing stream on another thread and share the data between a channel
         let (tx, rx) = channel::new();
 
         // Create a buffer for the thread
         let buffer = Arc::new(Mutex::new(
             InnerStream {
-                last_frame: Vec::new(),
+                framers: Vec::new(),
+                bass_framers: Vec::new(),
+                constant_q_framers: Vec::new(),
                 settings,
-                sample_rate: config.sample_rate.0,
-                sender: tx,
+                sample_rate: source.sample_rate(),
+                channels: source.channels(),
                 color,
-                effect
+                effect,
+                noise: NoiseReduction::new(noise_profile),
             }
         ));
         self.buffer = Some(buffer.clone());
 
-        let stream = device.build_input_stream(
-            &config,
-            move |data: &[f32], _info: &InputCallbackInfo| tick(data, buffer.clone()),
-            move |error| {
-                error!("Stream error: {:?}", error);
-            },
-            None
-        )?;
+        // The source only pushes its samples into a lock-free queue.
+        // The analysis runs on its own thread, so the real-time audio thread is never blocked
+        let capacity = (source.sample_rate() as f32 * source.channels() as f32 * Self::QUEUE_DURATION) as usize;
+        let (producer, consumer) = queue::new(capacity, source.channels());
 
-        stream.play()?;
-        self.cpal_stream = Some(stream);
+        self.running.store(true, SeqCst);
+        let running = self.running.clone();
+        self.analysis_thread = Some(thread::spawn(move || {
+            Self::analyze(consumer, buffer, tx, running)
+        }));
+
+        source.start(producer)?;
+        self.source = Some(source);
 
         Ok(rx)
     }
 
+    /// Process all samples of the queue, until the source was dropped or the stream was closed
+    fn analyze(mut consumer: SampleConsumer, buffer: Arc<Mutex<InnerStream>>, mut sender: Sender, running: Arc<AtomicBool>) {
+        let mut samples = Vec::new();
+        let mut context = AnalysisContext::new();
+
+        while running.load(SeqCst) {
+            // Check before reading, so the remaining samples of a finished source are not lost
+            let abandoned = consumer.is_abandoned();
+
+            if consumer.pop(&mut samples) == 0 {
+                if abandoned { break; }
+                thread::sleep(Self::IDLE_TIME);
+                continue;
+            }
+
+            let dropped = consumer.take_dropped();
+            if dropped > 0 {
+                warn!("Dropped {} samples, because the analysis was too slow", dropped);
+            }
+
+            tick(&samples, &buffer, &mut sender, &mut context);
+        }
+    }
+
+    /// Stop the audio source and the analysis thread
+    fn close(&mut self) {
+        self.source = None;
+        self.running.store(false, SeqCst);
+        if let Some(thread) = self.analysis_thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Get the properties of the currently opened audio source
+    pub fn info(&self) -> crate::Result<StreamInfo> {
+        let source = self.source.as_ref()
+            .ok_or(ControllerError::NoStream)?;
+
+        Ok(StreamInfo {
+            sample_format: source.sample_format(),
+            sample_rate: source.sample_rate(),
+            channels: source.channels(),
+            buffer_size: source.buffer_size(),
+        })
+    }
+
+    /// If the audio source has no more samples to deliver, this value will be true
+    pub fn is_finished(&self) -> bool {
+        self.source.as_ref()
+            .is_some_and(|source| source.is_finished())
+    }
+
     /// Update the stream settings
     /// If no stream has been started yet, this change has no effect
     pub fn update_settings(&mut self, settings: Settings) {
         // Update to access the stream
-        if let Some(guard) = self.buffer.as_deref() {
-            if let Ok(mut buffer) = guard.lock() {
-                buffer.settings = settings;
-            }
+        if let Some(guard) = self.buffer.as_deref()
+            && let Ok(mut buffer) = guard.lock() {
+            buffer.settings = settings;
         }
     }
 
@@ -106,31 +283,60 @@ impl Stream {
     /// If the effect produces his own color, these change will have no effect.
     pub fn update_color(&mut self, rgb: [u8; 3]) {
         // Try to access the stream and lock the buffer
-        if let Some(buffer) = self.buffer.as_deref() {
-            if let Ok(mut buffer) = buffer.lock() {
-                // Update the color, if available
-                buffer.color = rgb;
-            }
+        if let Some(buffer) = self.buffer.as_deref()
+            && let Ok(mut buffer) = buffer.lock() {
+            // Update the color, if available
+            buffer.color = rgb;
         }
     }
 
     /// Update the selected effect
     pub fn update_effect(&mut self, effect: Box<dyn AudioEffect>) {
         // Try to access the stream and lock the buffer
-        if let Some(buffer) = self.buffer.as_deref() {
-            if let Ok(mut buffer) = buffer.lock() {
-                buffer.effect = effect;
-            }
+        if let Some(buffer) = self.buffer.as_deref()
+            && let Ok(mut buffer) = buffer.lock() {
+            buffer.effect = effect;
         }
     }
 
+    /// Learn the noise profile of the input for the duration in seconds
+    pub fn learn_noise(&mut self, duration: f32) -> crate::Result<()> {
+        let buffer = self.buffer.as_deref()
+            .ok_or(ControllerError::NoStream)?;
+
+        if let Ok(mut buffer) = buffer.lock() {
+            buffer.noise.learn(duration);
+        }
+        Ok(())
+    }
+
+    /// Remove the noise profile of the input
+    pub fn clear_noise_profile(&mut self) {
+        if let Some(buffer) = self.buffer.as_deref()
+            && let Ok(mut buffer) = buffer.lock() {
+            buffer.noise.clear();
+        }
+    }
+
+    /// True, while the noise profile of the input is learned
+    pub fn is_learning_noise(&self) -> bool {
+        self.buffer.as_deref()
+            .and_then(|buffer| buffer.lock().ok())
+            .is_some_and(|buffer| buffer.noise.is_learning())
+    }
+
+    /// True, if a noise profile is subtracted from the input
+    pub fn has_noise_profile(&self) -> bool {
+        self.buffer.as_deref()
+            .and_then(|buffer| buffer.lock().ok())
+            .is_some_and(|buffer| buffer.noise.profile().is_some())
+    }
+
     pub fn is_color_selection_used(&self) -> crate::Result<bool> {
-        let guard = self.buffer.as_deref()
-            .ok_or(ControllerError::NoStream)?
-            .lock()
-            .unwrap();
+        let buffer = self.buffer.as_deref()
+            .ok_or(ControllerError::NoStream)?;
 
-        Ok(!guard.effect.disable_color_wheel())
+        Ok(buffer.lock().is_ok_and(|buffer| !buffer.effect.disable_color_wheel()))
     }
 
 }
\ No newline at end of file
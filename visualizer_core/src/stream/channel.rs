@@ -32,13 +32,15 @@ pub struct Sender {
 }
 
 impl Sender {
+    /// Send the frame to the receivers.
+    /// If a receiver was already dropped, its part of the frame is discarded.
     pub fn send(&mut self, frame: Frame) {
         if let Some(data) = frame.data {
-            self.tx_sacn.send(data).expect("gffgdfgf");
+            let _ = self.tx_sacn.send(data);
         }
 
         if let Some(view) = frame.view {
-            self.tx_view.send(view).expect("gffgdfgf");
+            let _ = self.tx_view.send(view);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
+use cpal::{FromSample, Sample};
+use rtrb::RingBuffer;
+
+/// Create a lock-free single producer single consumer queue for interleaved samples.
+/// The producer is used by the audio source and the consumer by the analysis thread.
+pub fn new(capacity: usize, channels: u16) -> (SampleProducer, SampleConsumer) {
+    let channels = channels.max(1) as usize;
+    // Only complete frames are stored in the queue
+    let capacity = capacity.max(channels) / channels * channels;
+
+    let (producer, consumer) = RingBuffer::<f32>::new(capacity);
+    let dropped = Arc::new(AtomicUsize::new(0));
+
+    let producer = SampleProducer { producer, channels, dropped: dropped.clone() };
+    let consumer = SampleConsumer { consumer, channels, dropped };
+
+    (producer, consumer)
+}
+
+/// The writing end of the sample queue.
+/// None of the methods block or allocate, so they can be used inside a real-time audio callback.
+pub struct SampleProducer {
+    producer: rtrb::Producer<f32>,
+    channels: usize,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl SampleProducer {
+
+    /// Convert the samples to f32 and push as many complete frames as possible into the queue.
+    /// Returns the amount of pushed samples
+    pub fn try_push<T>(&mut self, data: &[T]) -> usize
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let available = self.producer.slots().min(data.len());
+        let amount = available - available % self.channels;
+
+        match self.producer.write_chunk_uninit(amount) {
+            Ok(chunk) => chunk.fill_from_iter(data[..amount].iter().map(|it| it.to_sample::<f32>())),
+            Err(_) => 0,
+        }
+    }
+
+    /// Push the samples into the queue.
+    /// If the queue is full, the remaining samples are dropped and counted.
+    pub fn push_or_drop<T>(&mut self, data: &[T])
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let pushed = self.try_push(data);
+        if pushed < data.len() {
+            self.dropped.fetch_add(data.len() - pushed, Relaxed);
+        }
+    }
+}
+
+/// The reading end of the sample queue
+pub struct SampleConsumer {
+    consumer: rtrb::Consumer<f32>,
+    channels: usize,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl SampleConsumer {
+
+    /// Replace the content of the buffer with all complete frames which are available in the queue.
+    /// Returns the amount of received samples
+    pub fn pop(&mut self, buffer: &mut Vec<f32>) -> usize {
+        buffer.clear();
+
+        let available = self.consumer.slots();
+        let amount = available - available % self.channels;
+
+        if let Ok(chunk) = self.consumer.read_chunk(amount) {
+            let (first, second) = chunk.as_slices();
+            buffer.extend_from_slice(first);
+            buffer.extend_from_slice(second);
+            chunk.commit_all();
+        }
+
+        buffer.len()
+    }
+
+    /// Get the amount of samples, which were dropped since the last call
+    pub fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Relaxed)
+    }
+
+    /// If the producer was dropped, this value will be true
+    pub fn is_abandoned(&self) -> bool {
+        self.consumer.is_abandoned()
+    }
+}
@@ -0,0 +1,405 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{error, info};
+
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{BufferSize, FromSample, InputCallbackInfo, SampleFormat, SampleRate, SizedSample, SupportedBufferSize};
+
+use super::StreamConfigRequest;
+use super::queue::SampleProducer;
+use crate::ControllerError;
+
+/// A source of audio samples which feeds the audio stream.
+/// The source stops delivering samples when it is dropped.
+pub trait AudioSource {
+
+    /// The sample rate of the delivered samples
+    fn sample_rate(&self) -> u32;
+
+    /// The amount of interleaved channels of the delivered samples
+    fn channels(&self) -> u16;
+
+    /// The original sample format, before the samples were converted to f32
+    fn sample_format(&self) -> SampleFormat;
+
+    /// The amount of frames per callback, if the source uses a fixed size
+    fn buffer_size(&self) -> Option<u32> { None }
+
+    /// Start to deliver the interleaved samples to the producer
+    fn start(&mut self, producer: SampleProducer) -> Result<(), Box<dyn Error>>;
+
+    /// If the source has no more samples to deliver, this value will be true
+    fn is_finished(&self) -> bool { false }
+
+}
+
+
+/// Live audio source, which captures the samples of a cpal input device
+pub struct DeviceSource {
+    device: cpal::Device,
+    config: cpal::StreamConfig,
+    sample_format: SampleFormat,
+    stream: Option<cpal::Stream>,
+}
+
+impl DeviceSource {
+
+    /// All sample formats which can be converted to f32, ordered by preference.
+    /// F32 needs no conversion, followed by the formats with the highest resolution.
+    pub const SUPPORTED_FORMATS: [SampleFormat; 10] = [
+        SampleFormat::F32,
+        SampleFormat::F64,
+        SampleFormat::I32,
+        SampleFormat::I64,
+        SampleFormat::I16,
+        SampleFormat::U32,
+        SampleFormat::U64,
+        SampleFormat::U16,
+        SampleFormat::I8,
+        SampleFormat::U8,
+    ];
+
+    /// Create a new source for the device with the given config
+    pub fn new(device: cpal::Device, config: cpal::SupportedStreamConfig, buffer_size: BufferSize) -> DeviceSource {
+        let sample_format = config.sample_format();
+        let mut config: cpal::StreamConfig = config.into();
+        config.buffer_size = buffer_size;
+
+        DeviceSource {
+            device,
+            config,
+            sample_format,
+            stream: None,
+        }
+    }
+
+    /// Create a new source with the supported config of the device, which is closest to the requested config
+    pub fn negotiate(device: cpal::Device, request: StreamConfigRequest) -> crate::Result<DeviceSource> {
+        let configs = device.supported_input_configs()
+            .map_err(|e| ControllerError::CPALError(e.into()))?
+            .collect::<Vec<cpal::SupportedStreamConfigRange>>();
+
+        let (config, buffer_size) = Self::select_config(&configs, &request)
+            .ok_or(ControllerError::NoSupportedConfig)?;
+
+        Ok(DeviceSource::new(device, config, buffer_size))
+    }
+
+    /// Select the supported config, which is closest to the requested config.
+    /// The channel count is matched first, then the sample rate and finally the preferred sample format.
+    /// Returns None, if no config has a sample format which can be converted to f32.
+    pub fn select_config(configs: &[cpal::SupportedStreamConfigRange], request: &StreamConfigRequest) -> Option<(cpal::SupportedStreamConfig, BufferSize)> {
+        let mut best: Option<((u32, u32, usize), &cpal::SupportedStreamConfigRange)> = None;
+        for config_range in configs {
+            // Skip all formats which can't be converted
+            let Some(preference) = Self::format_preference(config_range.sample_format()) else { continue };
+
+            let channel_distance = request.channels
+                .map_or(0, |channels| channels.abs_diff(config_range.channels()) as u32);
+            let rate_distance = request.sample_rate
+                .map_or(0, |rate| rate.abs_diff(Self::closest_sample_rate(config_range, rate)));
+
+            let score = (channel_distance, rate_distance, preference);
+            if best.as_ref().is_none_or(|(best, _)| score < *best) {
+                best = Some((score, config_range));
+            }
+        }
+
+        let (_, &config_range) = best?;
+
+        // Without a requested sample rate, the highest rate is used
+        let buffer_size = Self::closest_buffer_size(config_range.buffer_size(), request.buffer_size);
+        let config = match request.sample_rate {
+            Some(rate) => {
+                let rate = Self::closest_sample_rate(&config_range, rate);
+                config_range.with_sample_rate(SampleRate(rate))
+            }
+            None => config_range.with_max_sample_rate(),
+        };
+
+        Some((config, buffer_size))
+    }
+
+    /// Get the sample rate of the range, which is closest to the requested rate
+    fn closest_sample_rate(range: &cpal::SupportedStreamConfigRange, rate: u32) -> u32 {
+        rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0)
+    }
+
+    /// Get the buffer size, which is closest to the requested size.
+    /// If the device doesn't report its supported sizes, the default size will be used.
+    fn closest_buffer_size(supported: &SupportedBufferSize, requested: Option<u32>) -> BufferSize {
+        match (supported, requested) {
+            (SupportedBufferSize::Range { min, max }, Some(size)) => BufferSize::Fixed(size.clamp(*min, *max)),
+            _ => BufferSize::Default,
+        }
+    }
+
+    /// Get the preference of the sample format. A lower value is preferred.
+    /// If the format can't be converted to f32, None is returned.
+    pub fn format_preference(format: SampleFormat) -> Option<usize> {
+        Self::SUPPORTED_FORMATS.iter().position(|it| *it == format)
+    }
+
+    /// Build the input stream for the sample type T.
+    /// The callback only pushes the samples into the queue, because it runs on the real-time audio thread.
+    fn build_stream<T>(&self, mut producer: SampleProducer) -> Result<cpal::Stream, cpal::BuildStreamError>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        self.device.build_input_stream(
+            &self.config,
+            move |data: &[T], _info: &InputCallbackInfo| producer.push_or_drop(data),
+            move |error| {
+                error!("Stream error: {:?}", error);
+            },
+            None
+        )
+    }
+}
+
+impl AudioSource for DeviceSource {
+
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    fn channels(&self) -> u16 {
+        self.config.channels
+    }
+
+    fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    fn buffer_size(&self) -> Option<u32> {
+        match self.config.buffer_size {
+            BufferSize::Fixed(size) => Some(size),
+            BufferSize::Default => None,
+        }
+    }
+
+    fn start(&mut self, producer: SampleProducer) -> Result<(), Box<dyn Error>> {
+        // Build the stream with the native sample type of the device
+        let stream = match self.sample_format {
+            SampleFormat::F32 => self.build_stream::<f32>(producer),
+            SampleFormat::F64 => self.build_stream::<f64>(producer),
+            SampleFormat::I8 => self.build_stream::<i8>(producer),
+            SampleFormat::I16 => self.build_stream::<i16>(producer),
+            SampleFormat::I32 => self.build_stream::<i32>(producer),
+            SampleFormat::I64 => self.build_stream::<i64>(producer),
+            SampleFormat::U8 => self.build_stream::<u8>(producer),
+            SampleFormat::U16 => self.build_stream::<u16>(producer),
+            SampleFormat::U32 => self.build_stream::<u32>(producer),
+            SampleFormat::U64 => self.build_stream::<u64>(producer),
+            _ => Err(cpal::BuildStreamError::StreamConfigNotSupported),
+        }?;
+
+        stream.play()?;
+        self.stream = Some(stream);
+
+        Ok(())
+    }
+}
+
+
+/// Defines how fast a file source delivers its samples
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pacing {
+    /// Deliver the samples at the speed of the original recording
+    RealTime,
+    /// Deliver the samples as fast as the stream can process them
+    AsFastAsPossible,
+}
+
+/// The decoded samples of a file with the sample rate, the amount of channels and the original sample format
+type DecodedFile = (Vec<f32>, u32, u16, SampleFormat);
+
+/// Audio source, which plays a decoded WAV or FLAC file
+pub struct FileSource {
+    samples: Arc<Vec<f32>>,
+    sample_rate: u32,
+    channels: u16,
+    sample_format: SampleFormat,
+    pacing: Pacing,
+    running: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl FileSource {
+
+    /// The amount of frames which are delivered to the queue at once
+    const BLOCK_FRAMES: usize = 512;
+
+    /// The time to wait, if the queue is full
+    const RETRY_TIME: Duration = Duration::from_millis(1);
+
+    /// Open and decode an audio file.
+    /// The format is chosen by the file extension. Supported are WAV and FLAC files.
+    pub fn open<P: AsRef<Path>>(path: P, pacing: Pacing) -> crate::Result<FileSource> {
+        let path = path.as_ref();
+        let extension = path.extension()
+            .and_then(|it| it.to_str())
+            .map(|it| it.to_ascii_lowercase());
+
+        let (samples, sample_rate, channels, sample_format) = match extension.as_deref() {
+            Some("wav") => Self::decode_wav(path),
+            Some("flac") => Self::decode_flac(path),
+            _ => return Err(ControllerError::UnsupportedFileFormat),
+        }.map_err(ControllerError::AudioFileError)?;
+
+        info!("Opened file {:?} with {} channels at {} Hz ({})", path, channels, sample_rate, sample_format);
+        Ok(Self::from_samples(samples, sample_rate, channels, sample_format, pacing))
+    }
+
+    /// Create a source, which plays the decoded interleaved samples
+    pub(crate) fn from_samples(mut samples: Vec<f32>, sample_rate: u32, channels: u16, sample_format: SampleFormat, pacing: Pacing) -> FileSource {
+        // The queue only accepts whole frames, so an incomplete last frame is dropped.
+        // Otherwise the playback would wait forever for space for it
+        samples.truncate(samples.len() - samples.len() % channels.max(1) as usize);
+
+        FileSource {
+            samples: Arc::new(samples),
+            sample_rate,
+            channels,
+            sample_format,
+            pacing,
+            running: Arc::new(AtomicBool::new(false)),
+            finished: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    /// Get the sample format which can store integer samples with the given bit depth
+    fn integer_format(bits_per_sample: u32) -> SampleFormat {
+        match bits_per_sample {
+            0..=8 => SampleFormat::I8,
+            9..=16 => SampleFormat::I16,
+            _ => SampleFormat::I32,
+        }
+    }
+
+    fn decode_wav(path: &Path) -> Result<DecodedFile, Box<dyn Error>> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+
+        // Convert every sample to a f32 value between -1.0 and 1.0
+        let (samples, format) = match spec.sample_format {
+            hound::SampleFormat::Float => {
+                let samples = reader.samples::<f32>()
+                    .collect::<Result<Vec<_>, _>>()?;
+                (samples, SampleFormat::F32)
+            }
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                let samples = reader.samples::<i32>()
+                    .map(|it| it.map(|sample| sample as f32 / scale))
+                    .collect::<Result<Vec<_>, _>>()?;
+                (samples, Self::integer_format(spec.bits_per_sample as u32))
+            }
+        };
+
+        Ok((samples, spec.sample_rate, spec.channels, format))
+    }
+
+    fn decode_flac(path: &Path) -> Result<DecodedFile, Box<dyn Error>> {
+        let mut reader = claxon::FlacReader::open(path)?;
+        let info = reader.streaminfo();
+
+        // Convert every sample to a f32 value between -1.0 and 1.0
+        let scale = (1i64 << (info.bits_per_sample - 1)) as f32;
+        let samples = reader.samples()
+            .map(|it| it.map(|sample| sample as f32 / scale))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((samples, info.sample_rate, info.channels as u16, Self::integer_format(info.bits_per_sample)))
+    }
+
+    /// Stop the playback thread, if it is running
+    fn stop(&mut self) {
+        self.running.store(false, SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl AudioSource for FileSource {
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    fn buffer_size(&self) -> Option<u32> {
+        Some(Self::BLOCK_FRAMES as u32)
+    }
+
+    fn start(&mut self, mut producer: SampleProducer) -> Result<(), Box<dyn Error>> {
+        self.stop();
+
+        let samples = self.samples.clone();
+        let block_len = Self::BLOCK_FRAMES * self.channels as usize;
+        let samples_per_second = (self.sample_rate * self.channels as u32) as f64;
+        let pacing = self.pacing;
+
+        self.running.store(true, SeqCst);
+        self.finished.store(false, SeqCst);
+        let running = self.running.clone();
+        let finished = self.finished.clone();
+
+        self.thread = Some(thread::spawn(move || {
+            let start = Instant::now();
+            let mut position = 0;
+
+            while running.load(SeqCst) && position < samples.len() {
+                let end = (position + block_len).min(samples.len());
+
+                // Wait until the queue has enough space for the whole block, so no samples are lost
+                let mut block = &samples[position..end];
+                while running.load(SeqCst) && !block.is_empty() {
+                    let pushed = producer.try_push(block);
+                    block = &block[pushed..];
+                    if !block.is_empty() { thread::sleep(Self::RETRY_TIME); }
+                }
+                position = end;
+
+                // Wait until the delivered samples would have been played
+                if pacing == Pacing::RealTime {
+                    let deadline = start + Duration::from_secs_f64(position as f64 / samples_per_second);
+                    if let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+                        thread::sleep(remaining);
+                    }
+                }
+            }
+
+            info!("Audio file finished");
+            finished.store(true, SeqCst);
+        }));
+
+        Ok(())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(SeqCst)
+    }
+}
+
+impl Drop for FileSource {
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
@@ -0,0 +1,47 @@
+use crate::dsp::{AgcSettings, AutomaticGainControl};
+
+/// A frame with the given rms level in dBFS
+fn frame(level: f32) -> Vec<f32> {
+    let amplitude = 10.0_f32.powf(level / 20.0);
+    (0..512).map(|n| if n % 2 == 0 { amplitude } else { -amplitude }).collect()
+}
+
+/// The gate must only open above the hysteresis and close after the hold time below the noise floor
+#[test]
+fn test_noise_gate() {
+    let settings = AgcSettings { noise_floor: -60.0, hysteresis: 6.0, hold_time: 50.0, max_gain: 30.0 };
+    let mut agc = AutomaticGainControl::new();
+    let mut run = |level: f32, frames: usize| (0..frames)
+        .map(|_| agc.update(&frame(level), &settings, 0.01))
+        .collect::<Vec<f32>>();
+
+    // Noise and a level inside the hysteresis don't open the gate
+    assert!(run(-80.0, 10).iter().all(|it| *it == 0.0));
+    assert!(run(-57.0, 10).iter().all(|it| *it == 0.0));
+
+    // A signal opens the gate, which stays open inside the hysteresis
+    assert!(run(-40.0, 10).iter().all(|it| *it > 0.0));
+    assert!(run(-57.0, 10).iter().all(|it| *it > 0.0));
+
+    // Below the noise floor, the gate closes after the hold time
+    let gains = run(-80.0, 10);
+    assert!(gains[..5].iter().all(|it| *it > 0.0), "{:?}", gains);
+    assert!(gains[5..].iter().all(|it| *it == 0.0), "{:?}", gains);
+}
+
+/// Quiet signals are amplified to the target level, but never more than the max gain
+#[test]
+fn test_max_gain() {
+    let settings = AgcSettings { noise_floor: -90.0, hysteresis: 0.0, hold_time: 0.0, max_gain: 20.0 };
+    let mut agc = AutomaticGainControl::new();
+
+    let quiet = (0..2000).map(|_| agc.update(&frame(-60.0), &settings, 0.01)).last().unwrap();
+    assert!((quiet - 10.0).abs() < 1e-3, "{}", quiet);
+
+    let normal = (0..2000).map(|_| agc.update(&frame(-30.0), &settings, 0.01)).last().unwrap();
+    assert!((normal - 10.0_f32.powf(0.5)).abs() < 0.01, "{}", normal);
+
+    // A loud signal reduces the gain within a few frames
+    let loud = (0..10).map(|_| agc.update(&frame(-6.0), &settings, 0.01)).last().unwrap();
+    assert!(loud < 0.2, "{}", loud);
+}
@@ -0,0 +1,43 @@
+use crate::dsp::{BeatTracker, Onset};
+
+/// An onset train with 120 BPM and an accent on every fourth onset must be tracked with its beats and downbeats
+#[test]
+fn test_beat_tracking() {
+    // 100 frames per second, so a beat every 50 frames
+    let (frame_interval, period) = (0.01, 50);
+    let mut tracker = BeatTracker::new();
+
+    let mut beats = Vec::new();
+    let mut downbeats = Vec::new();
+    let mut bpm = 0.0;
+    for frame in 0..3000 {
+        let onset = match frame % period {
+            0 if frame % (4 * period) == 0 => Onset { strength: 1.0, detected: true },
+            0 => Onset { strength: 0.5, detected: true },
+            _ => Onset::default(),
+        };
+
+        let beat = tracker.update(onset, frame_interval);
+        if beat.beat { beats.push(frame); }
+        if beat.downbeat { downbeats.push(frame); }
+        bpm = beat.bpm;
+    }
+
+    assert!((bpm - 120.0).abs() < 1.0, "{} BPM", bpm);
+
+    // After a few seconds the beats fall on the onsets
+    let late_beats = beats.iter().filter(|it| **it >= 2000).collect::<Vec<_>>();
+    assert_eq!(late_beats.len(), 20);
+    for beat in late_beats {
+        let distance = (beat % period).min(period - beat % period);
+        assert!(distance <= 2, "beat at frame {}", beat);
+    }
+
+    // The downbeats fall on the accented onsets
+    let late_downbeats = downbeats.iter().filter(|it| **it >= 2000).collect::<Vec<_>>();
+    assert_eq!(late_downbeats.len(), 5);
+    for downbeat in late_downbeats {
+        let distance = (downbeat % (4 * period)).min(4 * period - downbeat % (4 * period));
+        assert!(distance <= 2, "downbeat at frame {}", downbeat);
+    }
+}
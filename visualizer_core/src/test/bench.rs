@@ -0,0 +1,49 @@
+use std::time::Instant;
+
+use crate::dsp::{AnalysisContext, WindowFunction};
+
+const FRAMES: usize = 1000;
+const SAMPLE_RATE: u32 = 48000;
+
+/// Measure the analysis of noise frames with the given frame size and amount of bins.
+/// If cached is false, the fft plan, the window and the mel matrix are created for every frame.
+fn measure(frame_size: usize, bins: usize, cached: bool) -> f64 {
+    // Deterministic noise as input
+    let mut seed = 1u32;
+    let frames = (0..FRAMES)
+        .map(|_| (0..frame_size).map(|_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+        }).collect::<Vec<f32>>())
+        .collect::<Vec<Vec<f32>>>();
+
+    let mut context = AnalysisContext::new();
+    let start = Instant::now();
+    for frame in frames.iter() {
+        if !cached { context = AnalysisContext::new(); }
+
+        let spectrum = context.transform.power_spectrum(frame, frame_size, WindowFunction::Hann);
+        context.melbank.apply(&spectrum, 20.0, 16000.0, bins, SAMPLE_RATE);
+    }
+
+    start.elapsed().as_secs_f64()
+}
+
+/// Compare the analysis with cached resources to an analysis, which creates them for every frame.
+///
+/// Run with: cargo test --release -p visualizer_core bench_analysis_context -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_analysis_context() {
+    for (frame_size, bins) in [(1024, 60), (8192, 240)] {
+        let uncached = measure(frame_size, bins, false);
+        let cached = measure(frame_size, bins, true);
+
+        println!("{} frames of {} samples with {} bins", FRAMES, frame_size, bins);
+        println!("  uncached: {:.2} ms ({:.1} µs per frame)", uncached * 1e3, uncached * 1e6 / FRAMES as f64);
+        println!("  cached:   {:.2} ms ({:.1} µs per frame)", cached * 1e3, cached * 1e6 / FRAMES as f64);
+        println!("  speedup:  {:.1}x", uncached / cached);
+
+        assert!(cached < uncached);
+    }
+}
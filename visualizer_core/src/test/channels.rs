@@ -0,0 +1,80 @@
+use crate::dsp::{split_channels, Beat, Onset, Percussion};
+use crate::effects::AudioData;
+use crate::stream::Settings;
+
+/// A stereo input is split into the mono average followed by the left and the right channel
+#[test]
+fn test_split_stereo() {
+    let data = [1.0, 3.0, 2.0, 4.0, 0.0, -2.0];
+
+    let signals = split_channels(&data, 2, true);
+    assert_eq!(signals, vec![vec![2.0, 3.0, -1.0], vec![1.0, 2.0, 0.0], vec![3.0, 4.0, -2.0]]);
+
+    // Without the per channel analysis only the downmix remains
+    assert_eq!(split_channels(&data, 2, false), vec![vec![2.0, 3.0, -1.0]]);
+}
+
+/// Every channel of a multichannel input keeps its position after the downmix
+#[test]
+fn test_split_multichannel() {
+    let data = [0.0, 3.0, 6.0, 3.0, 3.0, 6.0, 9.0, 6.0];
+
+    let signals = split_channels(&data, 4, true);
+    assert_eq!(signals.len(), 5);
+    assert_eq!(signals[0], vec![3.0, 6.0]);
+    assert_eq!(signals[1], vec![0.0, 3.0]);
+    assert_eq!(signals[2], vec![3.0, 6.0]);
+    assert_eq!(signals[3], vec![6.0, 9.0]);
+    assert_eq!(signals[4], vec![3.0, 6.0]);
+}
+
+/// A mono input is never analyzed twice, even if the per channel analysis is enabled
+#[test]
+fn test_split_mono() {
+    let data = [0.5, -0.5, 0.25];
+    assert_eq!(split_channels(&data, 1, true), vec![data.to_vec()]);
+}
+
+/// Create the audio data of a frame with the mono melbank and the melbank of every channel
+fn audio_data<'a>(melbank: &'a [f32], channel_melbanks: &'a [Vec<f32>]) -> AudioData<'a> {
+    AudioData {
+        melbank,
+        channel_melbanks,
+        constant_q: &[],
+        chroma: [0.0; 12],
+        key: None,
+        onset: Onset::default(),
+        beat: Beat::default(),
+        percussion: Percussion::default(),
+        harmonic_melbank: &[],
+        percussive_melbank: &[],
+        power_spectrum: &[],
+        frequencies: &[],
+        raw_data: &[],
+        settings: Settings::default(),
+        sample_rate: 44100,
+        frame_interval: 0.01,
+        color: [0, 0, 0],
+    }
+}
+
+/// The left and right melbank come from the first and second channel, or fall back to the mono melbank
+#[test]
+fn test_channel_melbanks() {
+    let mono = [0.5, 0.5];
+
+    let channels = [vec![1.0, 0.0], vec![0.0, 1.0]];
+    let data = audio_data(&mono, &channels);
+    assert_eq!(data.left_melbank(), &[1.0, 0.0]);
+    assert_eq!(data.right_melbank(), &[0.0, 1.0]);
+
+    // A single analyzed channel is used for both sides
+    let channels = [vec![1.0, 0.0]];
+    let data = audio_data(&mono, &channels);
+    assert_eq!(data.right_melbank(), &[1.0, 0.0]);
+
+    // Without separate channels the mono melbank is used
+    let data = audio_data(&mono, &[]);
+    assert_eq!(data.left_melbank(), &mono);
+    assert_eq!(data.right_melbank(), &mono);
+}
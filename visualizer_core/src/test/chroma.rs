@@ -0,0 +1,35 @@
+use crate::dsp::{chromagram, KeyEstimator, Mode};
+
+/// Every frequency must be added to its pitch class
+#[test]
+fn test_chromagram_pitch_classes() {
+    // A4, E5 and C4 with different power
+    let frequencies = [261.63, 440.0, 659.26, 10000.0];
+    let spectrum = [0.5, 1.0, 0.25, 100.0];
+
+    let chroma = chromagram(&spectrum, &frequencies);
+    assert_eq!(chroma[9], 1.0);
+    assert_eq!(chroma[0], 0.5);
+    assert_eq!(chroma[4], 0.25);
+    // Frequencies outside of the range are ignored
+    assert_eq!(chroma.iter().sum::<f32>(), 1.75);
+}
+
+/// The tonic triads must be detected as their keys
+#[test]
+fn test_key_estimation() {
+    let mut chroma = [0.0; 12];
+    // C, E and G
+    for pitch in [0, 4, 7] { chroma[pitch] = 1.0; }
+    let mut estimator = KeyEstimator::new();
+    let key = (0..500).map(|_| estimator.update(&chroma, 0.01)).last().flatten().unwrap();
+    assert_eq!((key.tonic, key.mode), (0, Mode::Major));
+    assert!(key.confidence > 0.5);
+
+    let mut chroma = [0.0; 12];
+    // A, C and E
+    for pitch in [9, 0, 4] { chroma[pitch] = 1.0; }
+    let mut estimator = KeyEstimator::new();
+    let key = (0..500).map(|_| estimator.update(&chroma, 0.01)).last().flatten().unwrap();
+    assert_eq!((key.tonic, key.mode), (9, Mode::Minor));
+}
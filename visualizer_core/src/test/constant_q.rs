@@ -0,0 +1,55 @@
+use std::f32::consts::PI;
+
+use crate::dsp::{ConstantQ, Framer};
+use crate::stream::Settings;
+
+/// The bins must be aligned to the notes and a sinusoid must peak in the bin of its note
+#[test]
+fn test_constant_q_note_bins() {
+    let frequencies = ConstantQ::frequencies(48, 12, 50.0);
+    // G1 is the note closest to 50 Hz
+    assert!((frequencies[0] - 48.999).abs() < 0.01);
+    // A2 and A4 are exact
+    assert!((frequencies[2] - 55.0).abs() < 0.01);
+    assert!((frequencies[38] - 440.0).abs() < 0.01);
+
+    let sample_rate = 48000;
+    let frame = (0..8192)
+        .map(|n| (2.0 * PI * 220.0 * n as f32 / sample_rate as f32).sin())
+        .collect::<Vec<f32>>();
+
+    let bins = ConstantQ::new().apply(&frame, 50.0, 48, 12, sample_rate);
+    let peak = (0..bins.len()).max_by(|a, b| bins[*a].total_cmp(&bins[*b])).unwrap();
+    assert_eq!(peak, 26);
+
+    // The energy leaks into the neighbour notes, but not further
+    assert!(bins[25] < bins[26] * 0.5 && bins[27] < bins[26] * 0.5);
+    assert!(bins[23] < bins[26] * 0.05 && bins[29] < bins[26] * 0.05);
+}
+
+/// With the default settings, the history must be long enough for the kernel of every bin, so even the lowest notes are resolved
+#[test]
+fn test_constant_q_default_settings() {
+    let settings = Settings::default();
+    let sample_rate = 48000;
+    let (min_freq, bins_per_octave) = (settings.min_frequency as f32, settings.bins_per_octave);
+
+    // The kernel of the lowest bin is much longer than the analysis window
+    let history = ConstantQ::history_length(min_freq, bins_per_octave, sample_rate);
+    assert!(history > 30 * settings.window_size, "{}", history);
+
+    // Feed a sine at A0 through a framer like the stream does
+    let signal = (0..2 * history)
+        .map(|n| (2.0 * PI * 27.5 * n as f32 / sample_rate as f32).sin())
+        .collect::<Vec<f32>>();
+    let frame = Framer::new(history.max(settings.window_size), settings.hop_size).push(&signal).pop().unwrap();
+    let bins = ConstantQ::new().apply(&frame, min_freq, settings.n_bins, bins_per_octave, sample_rate);
+
+    let note = ConstantQ::frequencies(settings.n_bins, bins_per_octave, min_freq).iter()
+        .position(|it| (it - 27.5).abs() < 0.01)
+        .unwrap();
+    let peak = (0..bins.len()).max_by(|a, b| bins[*a].total_cmp(&bins[*b])).unwrap();
+    assert_eq!(peak, note);
+    assert!(bins[note - 1] < bins[note] * 0.5 && bins[note + 1] < bins[note] * 0.5);
+    assert!(bins[note - 3] < bins[note] * 0.05 && bins[note + 3] < bins[note] * 0.05);
+}
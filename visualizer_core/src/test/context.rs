@@ -0,0 +1,28 @@
+use std::f32::consts::PI;
+
+use crate::dsp::{Transform, WindowFunction};
+
+/// The zero padded spectrum must have fft_size/2 + 1 bins and a sinusoid must peak at its own frequency
+#[test]
+fn test_zero_padded_spectrum() {
+    let (sample_rate, frequency) = (48000, 1000.0);
+    let frame = (0..1024)
+        .map(|n| (2.0 * PI * frequency * n as f32 / sample_rate as f32).sin())
+        .collect::<Vec<f32>>();
+
+    let mut transform = Transform::new();
+    let spectrum = transform.power_spectrum(&frame, 8192, WindowFunction::Hann);
+    let frequencies = transform.frequencies(sample_rate);
+    assert_eq!(spectrum.len(), 4097);
+    assert_eq!(frequencies.len(), 4097);
+    assert_eq!(frequencies[4096], 24000.0);
+
+    let peak = (0..spectrum.len())
+        .max_by(|a, b| spectrum[*a].total_cmp(&spectrum[*b]))
+        .unwrap();
+    assert!((frequencies[peak] - frequency).abs() <= sample_rate as f32 / 8192.0);
+
+    // The fft is never shorter than the frame
+    assert_eq!(Transform::fft_size(1024, 512), 1024);
+    assert_eq!(Transform::fft_size(1000, 0), 1024);
+}
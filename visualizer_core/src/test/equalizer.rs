@@ -0,0 +1,65 @@
+use crate::dsp::{EqPoint, FrequencyScale, GraphicEq};
+use crate::dsp::melbank::center_frequencies;
+
+/// The gain must be interpolated on a logarithmic frequency axis and held outside of the points
+#[test]
+fn test_gain_curve() {
+    // The points are sorted by the equalizer
+    let eq = GraphicEq::new(vec![
+        EqPoint { frequency: 8000.0, gain: 6.0 },
+        EqPoint { frequency: 100.0, gain: -12.0 },
+        EqPoint { frequency: 400.0, gain: 0.0 },
+    ]);
+
+    assert_eq!(eq.gain(20.0), -12.0);
+    assert_eq!(eq.gain(100.0), -12.0);
+    assert!((eq.gain(200.0) + 6.0).abs() < 1e-4);
+    assert_eq!(eq.gain(16000.0), 6.0);
+    assert_eq!(GraphicEq::new(Vec::new()).gain(1000.0), 0.0);
+}
+
+/// The equalizer must cut the bass bins and leave the other bins unchanged
+#[test]
+fn test_apply_on_melbank() {
+    let frequencies = center_frequencies(FrequencyScale::Mel, 30, 20.0, 12000.0);
+    let mut eq = GraphicEq::default();
+    let mut melbank = vec![1.0; 30];
+
+    // A flat equalizer doesn't change anything
+    eq.apply(&mut melbank, &frequencies);
+    assert!(melbank.iter().all(|it| *it == 1.0));
+
+    eq.set_point(0, EqPoint { frequency: 31.5, gain: -10.0 });
+    eq.set_point(1, EqPoint { frequency: 63.0, gain: -10.0 });
+    eq.apply(&mut melbank, &frequencies);
+    for (value, frequency) in melbank.iter().zip(frequencies.iter()) {
+        if *frequency <= 63.0 { assert!((value - 0.1).abs() < 1e-4, "{}: {}", frequency, value); }
+        if *frequency >= 125.0 { assert_eq!(*value, 1.0); }
+    }
+}
+
+/// Moved, added and removed points must keep the points sorted
+#[test]
+fn test_edit_points() {
+    let mut eq = GraphicEq::new(vec![
+        EqPoint { frequency: 100.0, gain: 0.0 },
+        EqPoint { frequency: 1000.0, gain: 0.0 },
+    ]);
+
+    // Move the first point above the second one
+    eq.set_point(0, EqPoint { frequency: 4000.0, gain: 3.0 });
+    assert_eq!(eq.points(), &[EqPoint { frequency: 1000.0, gain: 0.0 }, EqPoint { frequency: 4000.0, gain: 3.0 }]);
+
+    eq.add_point(EqPoint { frequency: 2000.0, gain: -3.0 });
+    assert_eq!(eq.points().iter().map(|it| it.frequency).collect::<Vec<f32>>(), vec![1000.0, 2000.0, 4000.0]);
+    assert!((eq.gain(2000.0) + 3.0).abs() < 1e-6);
+
+    eq.remove_point(1);
+    assert_eq!(eq.points().len(), 2);
+    assert!((eq.gain(2000.0) - 1.5).abs() < 1e-4);
+
+    // A clone keeps its own points
+    let clone = eq.clone();
+    eq.remove_point(0);
+    assert_eq!(clone.points().len(), 2);
+}
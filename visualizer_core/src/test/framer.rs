@@ -0,0 +1,30 @@
+use crate::dsp::Framer;
+
+/// The frames must not depend on the size of the incoming chunks
+#[test]
+fn test_framer_chunk_independence() {
+    let signal = (0..5000).map(|it| it as f32).collect::<Vec<f32>>();
+
+    let mut framer = Framer::new(1024, 256);
+    let expected = framer.push(&signal);
+
+    // Deliver the same signal in chunks of varying size
+    let mut framer = Framer::new(1024, 256);
+    let mut frames = Vec::new();
+    let mut position = 0;
+    for size in [1, 7, 480, 512, 33, 1024, 2000].iter().cycle() {
+        if position >= signal.len() { break; }
+        let end = (position + size).min(signal.len());
+        frames.extend(framer.push(&signal[position..end]));
+        position = end;
+    }
+
+    assert_eq!(frames, expected);
+    assert_eq!(frames.len(), signal.len() / 256);
+
+    // Every frame contains the latest samples in chronological order
+    let last = frames.last().unwrap();
+    assert_eq!(last.len(), 1024);
+    assert_eq!(last[1023], (frames.len() * 256 - 1) as f32);
+    assert_eq!(last[0], (frames.len() * 256 - 1024) as f32);
+}
@@ -0,0 +1,66 @@
+use crate::dsp::{split_bins, Hpss};
+use crate::dsp::melbank::fft_frequencies;
+
+/// A stable tone must end up in the harmonic part and a broadband click in the percussive part
+#[test]
+fn test_harmonic_percussive_separation() {
+    let mut hpss = Hpss::new();
+    let (frequencies, frame_interval) = (fft_frequencies(1025, 48000), 512.0 / 48000.0);
+
+    // A tone in a single fft bin
+    let mut tone = vec![0.0; 1025];
+    tone[40] = 1.0;
+    for _ in 0..20 {
+        hpss.separate(&tone, &frequencies, frame_interval);
+    }
+
+    // The tone with a click over all frequencies
+    let click = tone.iter().map(|it| it + 0.5).collect::<Vec<f32>>();
+    let (harmonic, percussive) = hpss.separate(&click, &frequencies, frame_interval);
+
+    assert!(harmonic[40] > 2.0 * percussive[40]);
+    assert!(percussive[100] > 0.9 * click[100]);
+
+    // Both parts add up to the spectrum
+    for ((h, p), s) in harmonic.iter().zip(percussive.iter()).zip(click.iter()) {
+        assert!((h + p - s).abs() < 1e-6);
+    }
+}
+
+/// The bins of another filterbank are split with the masks of the fft bin at their center frequency
+#[test]
+fn test_split_bins() {
+    let frequencies = (0..201).map(|it| it as f32 * 10.0).collect::<Vec<f32>>();
+    let mut harmonic = vec![0.0; 201];
+    let mut percussive = vec![0.0; 201];
+    (harmonic[10], percussive[10]) = (3.0, 1.0);
+    (harmonic[100], percussive[100]) = (0.0, 2.0);
+
+    let [harmonic, percussive] = split_bins(&[4.0, 4.0, 4.0], &[101.0, 1000.0, 1500.0], &harmonic, &percussive, &frequencies);
+    assert_eq!(harmonic, vec![3.0, 0.0, 2.0]);
+    assert_eq!(percussive, vec![1.0, 4.0, 2.0]);
+}
+
+/// A new tone must become harmonic after the same time with every hop size
+#[test]
+fn test_frame_rate_independence() {
+    let frequencies = fft_frequencies(1025, 48000);
+    let mut tone = vec![0.0; 1025];
+    tone[40] = 1.0;
+
+    let delays = [0.005, 0.02].map(|frame_interval| {
+        let mut hpss = Hpss::new();
+        for _ in 0..(0.5 / frame_interval) as usize {
+            hpss.separate(&vec![0.0; 1025], &frequencies, frame_interval);
+        }
+
+        // The time until the tone is more harmonic than percussive
+        (1..).find(|_| {
+            let (harmonic, percussive) = hpss.separate(&tone, &frequencies, frame_interval);
+            harmonic[40] > percussive[40]
+        }).unwrap() as f32 * frame_interval
+    });
+
+    assert!((delays[0] - delays[1]).abs() <= 0.02, "{:?}", delays);
+    assert!(delays[0] > 0.05 && delays[0] < 0.15, "{:?}", delays);
+}
@@ -0,0 +1,208 @@
+use crate::dsp::{bass_power_spectrum, power_spectrum, FilterNormalization, FrequencyScale, MelbankCache, Transform, WindowFunction};
+use crate::dsp::melbank::{center_frequencies, compute_filter_matrix, fft_frequencies};
+use crate::stream::Settings;
+
+/// Dense reference implementation of the mel matrix, which visits every fft bin for every mel bin
+fn dense_mel_matrix(n_bins: usize, n_fft_bins: usize, min_freq: f32, max_freq: f32, sample_rate: u32) -> Vec<Vec<f32>> {
+    let to_mel = |f: f32| 2595.0 * (1.0 + (f/700.0)).log10();
+    let to_heart = |m: f32| 700.0 * (10.0_f32.powf(m/2595.0) - 1.0);
+
+    let (min, max) = (to_mel(min_freq), to_mel(max_freq));
+    let delta = (max - min) / (n_bins + 1) as f32;
+    let edges = (0..n_bins+2).map(|b| to_heart(min + delta * b as f32)).collect::<Vec<f32>>();
+    let nyquist = (sample_rate / 2) as f32;
+    let frequencies = (0..n_fft_bins).map(|k| nyquist * k as f32 / (n_fft_bins - 1) as f32).collect::<Vec<f32>>();
+
+    let mut matrix = vec![vec![0.0; n_fft_bins]; n_bins];
+    for i in 0..n_bins {
+        let (lower, center, upper) = (edges[i], edges[i+1], edges[i+2]);
+        for (j, f) in frequencies.iter().enumerate() {
+            if *f >= lower && *f <= center { matrix[i][j] = (*f - lower) / (center - lower); }
+            if *f >= center && *f <= upper { matrix[i][j] = (upper - *f) / (upper - center); }
+        }
+    }
+
+    matrix
+}
+
+/// The sparse filters must produce the same result as the full matrix
+#[test]
+fn test_sparse_mel_matrix() {
+    let (n_bins, n_fft_bins, min_freq, max_freq, sample_rate) = (200, 4096, 20.0, 16000.0, 48000);
+    let sparse = compute_filter_matrix(FrequencyScale::Mel, FilterNormalization::None, n_bins, n_fft_bins, min_freq, max_freq, sample_rate);
+    let dense = dense_mel_matrix(n_bins, n_fft_bins, min_freq, max_freq, sample_rate);
+    assert_eq!(sparse.len(), dense.len());
+
+    // A spectrum where the fft bins have different values
+    let fft = (0..n_fft_bins).map(|it| (it % 17) as f32 + 1.0).collect::<Vec<f32>>();
+
+    for (filter, row) in sparse.iter().zip(dense.iter()) {
+        // Only the fft bins with a weight are stored
+        assert!(filter.weights.len() <= row.iter().filter(|it| **it > 0.0).count() + 2);
+
+        let expected = row.iter().zip(fft.iter()).map(|(a, b)| a * b).sum::<f32>();
+        assert!((filter.apply(&fft) - expected).abs() <= expected.abs() * 1e-4 + 1e-6);
+    }
+}
+
+/// The bins below the crossover must be taken from the bass spectrum and all other bins from the short spectrum
+#[test]
+fn test_multi_resolution_melbank() {
+    let (n_bins, min_freq, max_freq, sample_rate, crossover) = (60, 20.0, 12000.0, 48000, 250.0);
+    let fft = (0..1025).map(|it| (it % 13) as f32 + 1.0).collect::<Vec<f32>>();
+    let bass_fft = (0..4097).map(|it| (it % 7) as f32 + 1.0).collect::<Vec<f32>>();
+
+    let short = MelbankCache::new().apply(&fft, min_freq, max_freq, n_bins, sample_rate);
+    let long = MelbankCache::new().apply(&bass_fft, min_freq, max_freq, n_bins, sample_rate);
+    let merged = MelbankCache::new().apply_multi_resolution(&fft, &bass_fft, (1024, 4096), crossover, min_freq, max_freq, n_bins, sample_rate);
+    assert_eq!(merged.len(), n_bins);
+
+    // The center frequencies of the mel bins rise, so the bass bins are a prefix of the melbank
+    let bass_bins = center_frequencies(FrequencyScale::Mel, n_bins, min_freq, max_freq).iter()
+        .take_while(|it| **it < crossover)
+        .count();
+    assert!(bass_bins > 0 && bass_bins < n_bins);
+    assert_eq!(merged[bass_bins..], short[bass_bins..]);
+
+    // Without normalization the bass bins are scaled by the ratio of the fft sizes and the frame lengths
+    let scale = (1024.0 / 4096.0) * (1024.0 / 4096.0);
+    for (value, expected) in merged[..bass_bins].iter().zip(long.iter()) {
+        assert!((value - expected * scale).abs() <= expected * scale * 1e-5);
+    }
+}
+
+/// Every scale must convert its values back to the same frequency
+#[test]
+fn test_frequency_scale_round_trip() {
+    let scales = [FrequencyScale::Mel, FrequencyScale::Bark, FrequencyScale::Erb, FrequencyScale::Logarithmic { bands_per_octave: 3 }, FrequencyScale::Linear];
+
+    for scale in scales {
+        for f in [20.0, 100.0, 440.0, 1000.0, 8000.0, 20000.0] {
+            let result = scale.to_heart(scale.from_heart(f));
+            assert!((result - f).abs() <= f * 1e-3, "{:?} converts {} Hz to {} Hz", scale, f, result);
+        }
+    }
+}
+
+/// Every third octave band must be centered on the base-2 frequencies of IEC 61260, independent of the amount of bins
+#[test]
+fn test_logarithmic_filters() {
+    let scale = FrequencyScale::Logarithmic { bands_per_octave: 3 };
+    // The nominal bands from 25 Hz to 16 kHz
+    assert_eq!(scale.band_count(20.0, 16000.0), Some(29));
+    let centers = center_frequencies(scale, 60, 20.0, 16000.0);
+    assert_eq!(centers.len(), 29);
+    assert!((centers[16] - 1000.0).abs() < 0.01, "{}", centers[16]);
+    for (i, center) in centers.iter().enumerate() {
+        let expected = 1000.0 * 2.0_f32.powf((i as f32 - 16.0) / 3.0);
+        assert!((center - expected).abs() <= expected * 1e-4, "{} != {}", center, expected);
+    }
+
+    let matrix = compute_filter_matrix(scale, FilterNormalization::None, 60, 8193, 20.0, 16000.0, 48000);
+    let frequencies = fft_frequencies(8193, 48000);
+    assert_eq!(matrix.len(), 29);
+
+    // The peak of every filter is at its center frequency
+    let peaks = matrix.iter()
+        .map(|filter| {
+            let peak = (0..filter.weights.len()).max_by(|a, b| filter.weights[*a].total_cmp(&filter.weights[*b])).unwrap();
+            frequencies[filter.start + peak]
+        })
+        .collect::<Vec<f32>>();
+
+    // Compare the high bins, where the fft resolution is fine enough
+    for pair in peaks[15..].windows(2) {
+        let ratio = pair[1] / pair[0];
+        assert!((ratio - 2.0_f32.powf(1.0 / 3.0)).abs() < 0.02, "ratio {}", ratio);
+    }
+}
+
+/// With an even amount of bands per octave, 1 kHz must be the edge between two bands
+#[test]
+fn test_even_octave_bands() {
+    let scale = FrequencyScale::Logarithmic { bands_per_octave: 6 };
+    let centers = center_frequencies(scale, 1, 500.0, 2000.0);
+    assert_eq!(scale.band_count(500.0, 2000.0), Some(12));
+    assert_eq!(centers.len(), 12);
+    assert!((centers[5] - 1000.0 * 2.0_f32.powf(-1.0 / 12.0)).abs() < 0.01);
+    assert!((centers[6] - 1000.0 * 2.0_f32.powf(1.0 / 12.0)).abs() < 0.01);
+
+    // Whole octaves are centered on 1 kHz
+    let octaves = center_frequencies(FrequencyScale::Logarithmic { bands_per_octave: 1 }, 1, 20.0, 20000.0);
+    let expected = (-5..=4).map(|it| 1000.0 * 2.0_f32.powi(it)).collect::<Vec<f32>>();
+    assert_eq!(octaves.len(), expected.len());
+    assert!(octaves.iter().zip(expected).all(|(a, b)| (a - b).abs() <= b * 1e-4), "{:?}", octaves);
+    assert_eq!(FrequencyScale::Mel.band_count(20.0, 20000.0), None);
+}
+
+/// Average power spectrum of white noise
+fn white_noise_spectrum(fft_size: usize, frames: usize) -> Vec<f32> {
+    let mut seed: u32 = 12345;
+    let mut transform = Transform::new();
+    let mut average = vec![0.0; fft_size / 2 + 1];
+
+    for _ in 0..frames {
+        let frame = (0..fft_size).map(|_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+        }).collect::<Vec<f32>>();
+
+        let spectrum = transform.power_spectrum(&frame, fft_size, WindowFunction::Hann);
+        average.iter_mut().zip(spectrum).for_each(|(a, b)| *a += b / frames as f32);
+    }
+
+    average
+}
+
+/// With area normalization every bin must have the same energy for white noise
+#[test]
+fn test_area_normalization_white_noise() {
+    let spectrum = white_noise_spectrum(2048, 400);
+
+    let mut cache = MelbankCache::new();
+    cache.normalization = FilterNormalization::Area;
+    let melbank = cache.apply(&spectrum, 100.0, 16000.0, 40, 48000);
+    let mean = melbank.iter().sum::<f32>() / melbank.len() as f32;
+    for value in melbank.iter() {
+        assert!((value / mean - 1.0).abs() < 0.15, "{} differs from the mean {}", value, mean);
+    }
+
+    // Without normalization the wide high bins collect much more energy
+    let melbank = MelbankCache::new().apply(&spectrum, 100.0, 16000.0, 40, 48000);
+    assert!(melbank[39] > 10.0 * melbank[0]);
+}
+
+/// With area normalization, white noise must have the same energy in the bins of the bass spectrum and the short spectrum
+#[test]
+fn test_area_normalization_multi_resolution() {
+    let (frame_length, bass_frame_length, sample_rate) = (1024, 4096, 48000);
+    let settings = Settings { pre_emphasis: 0.0, fft_size: 2048, ..Settings::default() };
+    let (mut transform, mut bass_transform) = (Transform::new(), Transform::new());
+
+    // The short frame is the end of the bass frame, like the framers deliver them
+    let mut seed: u32 = 12345;
+    let (mut spectrum, mut bass_spectrum) = (Vec::<f32>::new(), Vec::<f32>::new());
+    let frames = 200;
+    for _ in 0..frames {
+        let bass_frame = (0..bass_frame_length).map(|_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+        }).collect::<Vec<f32>>();
+        let frame = &bass_frame[bass_frame_length - frame_length..];
+
+        let short = power_spectrum(frame, settings.fft_size, &settings, &mut transform);
+        let long = bass_power_spectrum(frame_length, &bass_frame, &settings, &mut bass_transform);
+        spectrum.resize(short.len(), 0.0);
+        bass_spectrum.resize(long.len(), 0.0);
+        spectrum.iter_mut().zip(short).for_each(|(a, b)| *a += b / frames as f32);
+        bass_spectrum.iter_mut().zip(long).for_each(|(a, b)| *a += b / frames as f32);
+    }
+
+    let mut cache = MelbankCache::new();
+    cache.normalization = FilterNormalization::Area;
+    let melbank = cache.apply_multi_resolution(&spectrum, &bass_spectrum, (frame_length, bass_frame_length), 250.0, 50.0, 16000.0, 40, sample_rate);
+    let mean = melbank.iter().sum::<f32>() / melbank.len() as f32;
+    for (i, value) in melbank.iter().enumerate() {
+        assert!((value / mean - 1.0).abs() < 0.2, "bin {}: {} differs from the mean {}", i, value, mean);
+    }
+}
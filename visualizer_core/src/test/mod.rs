@@ -1,2 +1,26 @@
+mod sacn;
+mod source;
+mod channels;
+mod framer;
+mod melbank;
+mod window;
+mod context;
+mod constant_q;
+mod chroma;
+mod onset;
+mod beat;
+mod percussion;
+mod smoothing;
+mod hpss;
+mod agc;
+mod weighting;
+mod equalizer;
+mod noise;
+mod bench;
 
-mod sacn;
\ No newline at end of file
+/// Create a directory in the temp directory, which is unique for the process and the test
+fn temp_dir(test: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("visualizer_{}_{}", std::process::id(), test));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
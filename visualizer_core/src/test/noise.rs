@@ -0,0 +1,131 @@
+use crate::dsp::{NoiseProfile, NoiseReduction, NoiseSettings, WindowFunction};
+
+/// The settings of a mono profile with 4 short and no bass bins
+const SETTINGS: NoiseSettings = NoiseSettings {
+    sample_rate: 48000,
+    signals: 1,
+    window_size: 6,
+    fft_size: 6,
+    bass_window_size: 0,
+    bass_fft_size: 0,
+    pre_emphasis: 0.9,
+    window_function: WindowFunction::Hann,
+};
+
+/// Subtract the profile of the mono signal, if it matches the settings
+fn subtract(noise: &mut NoiseReduction, spectrum: &mut [f32], bass: bool, settings: &NoiseSettings, gain: f32) {
+    if let Some(profile) = noise.matching_profile(settings) {
+        profile.subtract(0, spectrum, bass, gain);
+    }
+}
+
+/// The learned profile must be the average noise without the gain and must be removed from the spectrum
+#[test]
+fn test_learn_and_subtract() {
+    let mut noise = NoiseReduction::new(None);
+    noise.learn(0.375);
+
+    // The learning stops after the duration, frames with a closed gate are ignored
+    for frame in 0..20 {
+        let level = if frame % 2 == 0 { 1.0 } else { 3.0 };
+        let gain = if frame == 4 || frame == 5 { 0.0 } else { 2.0 };
+        noise.update(&[vec![4.0 * level; 4]], &[], &SETTINGS, gain, 0.0625);
+    }
+    assert!(!noise.is_learning());
+    let profile = noise.profile().unwrap();
+    assert_eq!(profile.spectra, vec![vec![2.0; 4]]);
+    assert!(profile.bass_spectra.is_empty());
+
+    // Only the bins above the noise remain
+    let mut spectrum = [0.0, 4.0, 12.0, 100.0];
+    subtract(&mut noise, &mut spectrum, false, &SETTINGS, 1.0);
+    assert_eq!(spectrum, [0.0, 1.0, 9.0, 97.0]);
+
+    // The spectral floor keeps a part of the noisy bins
+    let mut spectrum = [2.0; 4];
+    subtract(&mut noise, &mut spectrum, false, &SETTINGS, 1.0);
+    assert_eq!(spectrum, [0.02; 4]);
+
+    // Spectra with other settings or without a learned bass profile are not changed
+    let mut spectrum = [2.0; 4];
+    subtract(&mut noise, &mut spectrum, false, &NoiseSettings { sample_rate: 44100, ..SETTINGS }, 1.0);
+    subtract(&mut noise, &mut spectrum, false, &NoiseSettings { window_size: 4, ..SETTINGS }, 1.0);
+    subtract(&mut noise, &mut spectrum, false, &NoiseSettings { signals: 3, ..SETTINGS }, 1.0);
+    subtract(&mut noise, &mut spectrum, false, &NoiseSettings { pre_emphasis: 0.0, ..SETTINGS }, 1.0);
+    subtract(&mut noise, &mut spectrum, false, &NoiseSettings { window_function: WindowFunction::Hamming, ..SETTINGS }, 1.0);
+    subtract(&mut noise, &mut spectrum, true, &SETTINGS, 1.0);
+    assert_eq!(spectrum, [2.0; 4]);
+}
+
+/// The bass spectrum must be taken from the bass profile, even if both spectra have the same length
+#[test]
+fn test_bass_profile() {
+    let settings = NoiseSettings { fft_size: 2, bass_window_size: 2, bass_fft_size: 2, ..SETTINGS };
+    let mut noise = NoiseReduction::new(None);
+    noise.learn(0.01);
+    noise.update(&[vec![1.0, 1.0]], &[vec![4.0, 4.0]], &settings, 1.0, 0.01);
+
+    let (mut spectrum, mut bass_spectrum) = ([10.0; 2], [10.0; 2]);
+    subtract(&mut noise, &mut spectrum, false, &settings, 1.0);
+    subtract(&mut noise, &mut bass_spectrum, true, &settings, 1.0);
+    assert_eq!(spectrum, [8.5; 2]);
+    assert_eq!(bass_spectrum, [4.0; 2]);
+
+    // A longer bass window with the same fft size doesn't match the profile
+    let mut bass_spectrum = [10.0; 2];
+    subtract(&mut noise, &mut bass_spectrum, true, &NoiseSettings { bass_window_size: 1, ..settings }, 1.0);
+    assert_eq!(bass_spectrum, [10.0; 2]);
+}
+
+/// Every channel must get the noise, which was learned from its own signal
+#[test]
+fn test_channel_profiles() {
+    let settings = NoiseSettings { signals: 3, fft_size: 2, ..SETTINGS };
+    let mut noise = NoiseReduction::new(None);
+    noise.learn(0.01);
+    // Only the left channel is noisy, so the downmix has half of its noise
+    noise.update(&[vec![1.0; 2], vec![2.0; 2], vec![0.0; 2]], &[], &settings, 1.0, 0.01);
+
+    let profile = noise.matching_profile(&settings).unwrap();
+    let mut spectra = [[10.0; 2]; 3];
+    for (i, spectrum) in spectra.iter_mut().enumerate() {
+        profile.subtract(i, spectrum, false, 1.0);
+    }
+    assert_eq!(spectra, [[8.5; 2], [7.0; 2], [10.0; 2]]);
+}
+
+/// A learned profile must be saved and loaded again for the same path
+#[test]
+fn test_persistence() {
+    let dir = super::temp_dir("test_persistence");
+    let path = dir.join("device.noise");
+    let settings = NoiseSettings {
+        sample_rate: 44100,
+        signals: 2,
+        window_size: 2,
+        fft_size: 2,
+        bass_window_size: 3,
+        bass_fft_size: 4,
+        window_function: WindowFunction::Kaiser { beta: 8.6 },
+        ..SETTINGS
+    };
+
+    let mut noise = NoiseReduction::new(Some(path.clone()));
+    assert!(noise.profile().is_none());
+    noise.learn(0.01);
+    noise.update(&[vec![0.5, 0.25], vec![1.0, 0.5]], &[vec![0.125; 3], vec![0.25; 3]], &settings, 1.0, 0.01);
+
+    let loaded = NoiseReduction::new(Some(path.clone()));
+    let expected = NoiseProfile {
+        settings,
+        spectra: vec![vec![0.5, 0.25], vec![1.0, 0.5]],
+        bass_spectra: vec![vec![0.125; 3], vec![0.25; 3]],
+    };
+    assert_eq!(loaded.profile(), Some(&expected));
+
+    // Clearing the profile removes the file
+    noise.clear();
+    assert!(noise.profile().is_none());
+    assert!(!path.exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,23 @@
+use crate::dsp::OnsetDetector;
+
+/// An onset, which moves the energy to other frequencies without changing the total energy, must be detected
+#[test]
+fn test_spectral_flux_onset() {
+    let low = (0..64).map(|it| if it < 16 { 1.0 } else { 0.0 }).collect::<Vec<f32>>();
+    let high = (0..64).map(|it| if (16..32).contains(&it) { 1.0 } else { 0.0 }).collect::<Vec<f32>>();
+    assert_eq!(low.iter().sum::<f32>(), high.iter().sum::<f32>());
+
+    let mut detector = OnsetDetector::new();
+    let onsets = (0..100)
+        .map(|frame| detector.update(if frame < 50 { &low } else { &high }, 0.01))
+        .collect::<Vec<_>>();
+
+    // The first frame has no previous frame, so only the change is detected
+    let detected = onsets.iter().enumerate()
+        .filter(|(_, onset)| onset.detected)
+        .map(|(frame, _)| frame)
+        .collect::<Vec<usize>>();
+    assert_eq!(detected, vec![50]);
+    assert_eq!(onsets[50].strength, 1.0);
+    assert_eq!(onsets[60].strength, 0.0);
+}
@@ -0,0 +1,111 @@
+use crate::dsp::PercussionAnalyzer;
+use crate::dsp::melbank::fft_frequencies;
+
+/// Every drum must only be detected, when its frequency band gets a hit
+#[test]
+fn test_drum_classification() {
+    let sample_rate = 48000;
+    let frequencies = fft_frequencies(1025, sample_rate);
+    let frame_interval = 512.0 / sample_rate as f32;
+    let hit = |spectrum: &mut [f32], range: (f32, f32)| {
+        for (power, frequency) in spectrum.iter_mut().zip(frequencies.iter()) {
+            if (range.0..range.1).contains(frequency) { *power += 10.0; }
+        }
+    };
+
+    let mut analyzer = PercussionAnalyzer::new();
+    let (mut kicks, mut snares, mut hihats) = (Vec::new(), Vec::new(), Vec::new());
+    for frame in 0..400 {
+        // Quiet noise floor
+        let mut spectrum = vec![0.001; 1025];
+        if frame % 40 == 0 { hit(&mut spectrum, (50.0, 100.0)); }
+        if frame % 40 == 20 { hit(&mut spectrum, (1500.0, 4000.0)); }
+        if frame % 10 == 5 { hit(&mut spectrum, (8000.0, 14000.0)); }
+
+        let percussion = analyzer.update(&spectrum, sample_rate, frame_interval);
+        if percussion.kick.detected { kicks.push(frame); }
+        if percussion.snare.detected { snares.push(frame); }
+        if percussion.hihat.detected { hihats.push(frame); }
+    }
+
+    // The first hits are needed to adjust the detectors
+    assert!(kicks.iter().filter(|it| **it >= 200).eq([200, 240, 280, 320, 360].iter()), "{:?}", kicks);
+    assert!(snares.iter().filter(|it| **it >= 200).eq([220, 260, 300, 340, 380].iter()), "{:?}", snares);
+    assert!(hihats.iter().filter(|it| **it >= 200).eq((205..400).step_by(10).collect::<Vec<_>>().iter()), "{:?}", hihats);
+}
+
+/// A broadband snare must neither be reported as hi-hat nor as kick
+#[test]
+fn test_broadband_snare() {
+    let sample_rate = 48000;
+    let frequencies = fft_frequencies(1025, sample_rate);
+    let frame_interval = 512.0 / sample_rate as f32;
+    let hit = |spectrum: &mut [f32], range: (f32, f32)| {
+        for (power, frequency) in spectrum.iter_mut().zip(frequencies.iter()) {
+            if (range.0..range.1).contains(frequency) { *power += 10.0; }
+        }
+    };
+
+    let mut analyzer = PercussionAnalyzer::new();
+    let (mut kicks, mut snares, mut hihats) = (Vec::new(), Vec::new(), Vec::new());
+    for frame in 0..400 {
+        let mut spectrum = vec![0.001; 1025];
+        // The snare covers every drum band
+        if frame % 40 == 20 { hit(&mut spectrum, (40.0, 16000.0)); }
+        if frame % 10 == 5 { hit(&mut spectrum, (8000.0, 14000.0)); }
+
+        let percussion = analyzer.update(&spectrum, sample_rate, frame_interval);
+        if percussion.kick.detected { kicks.push(frame); }
+        if percussion.snare.detected { snares.push(frame); }
+        if percussion.hihat.detected { hihats.push(frame); }
+
+        // After the detectors adjusted, the snare must not light the kick or the hi-hat
+        if frame >= 200 {
+            assert_eq!(percussion.kick.strength, 0.0, "kick at {}", frame);
+        }
+        if frame >= 200 && frame % 40 >= 20 && frame % 40 < 25 {
+            assert_eq!(percussion.hihat.strength, 0.0, "hi-hat at {}", frame);
+        }
+    }
+
+    assert!(kicks.is_empty(), "{:?}", kicks);
+    assert!(snares.iter().filter(|it| **it >= 200).eq([220, 260, 300, 340, 380].iter()), "{:?}", snares);
+    assert!(hihats.iter().all(|it| it % 10 == 5), "{:?}", hihats);
+    assert!(hihats.iter().filter(|it| **it >= 200).eq((205..400).step_by(10).collect::<Vec<_>>().iter()), "{:?}", hihats);
+}
+
+/// A kick and a snare at the same moment must both be reported
+#[test]
+fn test_simultaneous_kick_and_snare() {
+    let sample_rate = 48000;
+    let frequencies = fft_frequencies(1025, sample_rate);
+    let frame_interval = 512.0 / sample_rate as f32;
+    let hit = |spectrum: &mut [f32], range: (f32, f32)| {
+        for (power, frequency) in spectrum.iter_mut().zip(frequencies.iter()) {
+            if (range.0..range.1).contains(frequency) { *power += 10.0; }
+        }
+    };
+
+    let mut analyzer = PercussionAnalyzer::new();
+    let (mut kicks, mut snares, mut hihats) = (Vec::new(), Vec::new(), Vec::new());
+    for frame in 0..400 {
+        let mut spectrum = vec![0.001; 1025];
+        if frame % 40 == 0 {
+            hit(&mut spectrum, (50.0, 100.0));
+            hit(&mut spectrum, (1500.0, 4000.0));
+        }
+        if frame % 10 == 5 { hit(&mut spectrum, (8000.0, 14000.0)); }
+
+        let percussion = analyzer.update(&spectrum, sample_rate, frame_interval);
+        if percussion.kick.detected {
+            kicks.push(frame);
+            assert!(percussion.kick.strength > 0.5, "{}: {}", frame, percussion.kick.strength);
+        }
+        if percussion.snare.detected { snares.push(frame); }
+        if percussion.hihat.detected { hihats.push(frame); }
+    }
+
+    assert!(kicks.iter().filter(|it| **it >= 200).eq([200, 240, 280, 320, 360].iter()), "{:?}", kicks);
+    assert!(snares.iter().filter(|it| **it >= 200).eq([200, 240, 280, 320, 360].iter()), "{:?}", snares);
+    assert!(hihats.iter().filter(|it| **it >= 200).eq((205..400).step_by(10).collect::<Vec<_>>().iter()), "{:?}", hihats);
+}
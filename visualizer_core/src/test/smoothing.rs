@@ -0,0 +1,106 @@
+use crate::dsp::smoothing::{ExponentialFilter, KalmanFilter, MedianFilter, OneEuroFilter, Smoothing, SmoothingMethod};
+
+/// The step response after a fixed time must not depend on the frame interval
+#[test]
+fn test_frame_rate_independence() {
+    let step = |frame_interval: f32| {
+        let mut filter = ExponentialFilter::new(0.0, 50.0, 500.0);
+        let frames = (0.1 / frame_interval).round() as usize;
+        (0..frames).map(|_| filter.update(1.0, frame_interval)).last().unwrap()
+    };
+
+    // After two time constants, the filter reached 1 - e^-2 of the step
+    let expected = 1.0 - (-2.0_f32).exp();
+    for frame_interval in [0.001, 0.005, 0.01, 0.02] {
+        assert!((step(frame_interval) - expected).abs() < 1e-3, "{}: {}", frame_interval, step(frame_interval));
+    }
+}
+
+/// The release time must be used for a falling signal
+#[test]
+fn test_attack_and_release() {
+    let mut filter = ExponentialFilter::new(vec![0.0, 1.0], 10.0, 1000.0);
+    let mut values = vec![1.0, 0.0];
+    filter.update(&mut values, 0.01);
+
+    assert!(values[0] > 0.6);
+    assert!(values[1] > 0.99);
+}
+
+/// A single spike must be removed completely, while a step passes after half of the window
+#[test]
+fn test_median_filter() {
+    let mut filter = MedianFilter::<f32>::new(50.0);
+    let output = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0].map(|mut value| {
+        filter.smooth(&mut value, 0.01);
+        value
+    });
+
+    assert_eq!(output, [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+}
+
+/// The one-euro filter must follow fast changes faster than an exponential filter, which smooths jitter at rest equally
+#[test]
+fn test_one_euro_filter() {
+    let mut one_euro = OneEuroFilter::new(vec![0.0], 1.0, 1.0, 1.0);
+    let mut exponential = ExponentialFilter::new(vec![0.0], 160.0, 160.0);
+
+    let run = |filter: &mut dyn Smoothing<[f32]>, input: &[f32]| input.iter()
+        .map(|value| {
+            let mut values = [*value];
+            filter.smooth(&mut values, 0.01);
+            values[0]
+        })
+        .collect::<Vec<f32>>();
+    let jitter = (0..100).map(|i| if i % 2 == 0 { 0.01 } else { -0.01 }).collect::<Vec<f32>>();
+    let step = vec![1.0; 10];
+
+    // At rest, both filters keep the jitter small
+    let one_euro_jitter = run(&mut one_euro, &jitter);
+    let exponential_jitter = run(&mut exponential, &jitter);
+    assert!(one_euro_jitter[50..].iter().all(|it| it.abs() < 0.002), "{:?}", one_euro_jitter);
+    assert!(exponential_jitter[50..].iter().all(|it| it.abs() < 0.002), "{:?}", exponential_jitter);
+
+    // A step is followed much faster
+    let one_euro_step = run(&mut one_euro, &step);
+    let exponential_step = run(&mut exponential, &step);
+    assert!(one_euro_step[9] > 0.9, "{:?}", one_euro_step);
+    assert!(exponential_step[9] < 0.5, "{:?}", exponential_step);
+}
+
+/// The Kalman filter must converge to a constant signal and reduce the noise of the measurements
+#[test]
+fn test_kalman_filter() {
+    let mut filter = KalmanFilter::new(vec![0.0; 2], 0.1, 0.1);
+    let mut seed = 1u32;
+    let (mut input_error, mut output_error) = (0.0, 0.0);
+    let mut values = [0.0; 2];
+    for frame in 0..500 {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+        values = [1.0 + noise, 2.0];
+        filter.smooth(&mut values, 0.01);
+
+        if frame >= 100 {
+            input_error += noise.abs();
+            output_error += (values[0] - 1.0).abs();
+        }
+    }
+
+    assert!((values[1] - 2.0).abs() < 1e-3);
+    assert!(output_error < input_error / 2.0, "{} {}", output_error, input_error);
+}
+
+/// The filter of every smoothing method must follow a step to its new level
+#[test]
+fn test_smoothing_methods() {
+    for method in [SmoothingMethod::Exponential, SmoothingMethod::OneEuro, SmoothingMethod::Median, SmoothingMethod::Kalman] {
+        let mut filter = method.create();
+        let mut values = vec![1.0; 4];
+        for _ in 0..500 {
+            values.fill(1.0);
+            filter.smooth(&mut values, 0.01);
+        }
+        assert!(values.iter().all(|it| (it - 1.0).abs() < 0.01), "{:?}: {:?}", method, values);
+    }
+}
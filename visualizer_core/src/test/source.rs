@@ -0,0 +1,169 @@
+use std::thread;
+use std::time::Duration;
+
+use cpal::{BufferSize, SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfigRange};
+
+use crate::stream::{queue, StreamConfigRequest};
+use crate::stream::source::{AudioSource, DeviceSource, FileSource, Pacing};
+
+/// Play the source as fast as possible and collect all delivered samples
+fn play(source: &mut FileSource) -> Vec<f32> {
+    // Use a small queue, so the source has to wait for the consumer
+    let (producer, mut consumer) = queue::new(4096, source.channels());
+    source.start(producer).unwrap();
+
+    let mut received = Vec::<f32>::new();
+    let mut samples = Vec::new();
+    loop {
+        // Check before reading, so the last samples are not lost
+        let finished = source.is_finished();
+        if consumer.pop(&mut samples) > 0 {
+            received.extend_from_slice(&samples);
+        } else if finished {
+            break;
+        } else {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    assert_eq!(consumer.take_dropped(), 0);
+
+    received
+}
+
+/// Write a short stereo wav file, play it as fast as possible and compare the delivered samples
+#[test]
+fn test_wav_file_source() {
+    let dir = super::temp_dir("test_wav_file_source");
+    let path = dir.join("source.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    // One second of a 440 Hz sine on both channels
+    let expected = (0..44100*2)
+        .map(|i| ((i / 2) as f32 * 440.0 * std::f32::consts::TAU / 44100.0).sin() * 0.5)
+        .collect::<Vec<f32>>();
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for sample in expected.iter() {
+        writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut source = FileSource::open(&path, Pacing::AsFastAsPossible).unwrap();
+    assert_eq!(source.sample_rate(), 44100);
+    assert_eq!(source.channels(), 2);
+    assert_eq!(source.sample_format(), cpal::SampleFormat::I16);
+
+    let received = play(&mut source);
+    assert_eq!(received.len(), expected.len());
+    for (a, b) in received.iter().zip(expected.iter()) {
+        assert!((a - b).abs() < 0.001);
+    }
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/// An incomplete last frame must be dropped, so the playback still finishes
+#[test]
+fn test_incomplete_last_frame() {
+    let samples = (0..1001).map(|it| it as f32 / 1001.0).collect::<Vec<f32>>();
+    let mut source = FileSource::from_samples(samples.clone(), 44100, 2, cpal::SampleFormat::F32, Pacing::AsFastAsPossible);
+    assert_eq!(play(&mut source), samples[..1000]);
+}
+
+/// Play the stereo flac fixture, which contains a 441 Hz sine on the left and the inverted sine on the right
+#[test]
+fn test_flac_file_source() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test/fixtures/sine.flac");
+    let mut source = FileSource::open(path, Pacing::AsFastAsPossible).unwrap();
+    assert_eq!(source.sample_rate(), 44100);
+    assert_eq!(source.channels(), 2);
+    assert_eq!(source.sample_format(), cpal::SampleFormat::I16);
+
+    let received = play(&mut source);
+    assert_eq!(received.len(), 2000);
+    for (i, frame) in received.chunks(2).enumerate() {
+        let expected = (i as f32 * 441.0 * std::f32::consts::TAU / 44100.0).sin() * 0.5;
+        assert!((frame[0] - expected).abs() < 0.001, "{}: {} != {}", i, frame[0], expected);
+        assert!((frame[1] + expected).abs() < 0.001, "{}: {} != {}", i, frame[1], -expected);
+    }
+}
+
+/// Create a supported config range with a buffer size between 64 and 4096 frames
+fn config_range(channels: u16, min_rate: u32, max_rate: u32, format: SampleFormat) -> SupportedStreamConfigRange {
+    let buffer_size = SupportedBufferSize::Range { min: 64, max: 4096 };
+    SupportedStreamConfigRange::new(channels, SampleRate(min_rate), SampleRate(max_rate), buffer_size, format)
+}
+
+/// A config which supports the requested values exactly is selected without changes
+#[test]
+fn test_select_exact_config() {
+    let configs = [
+        config_range(1, 8000, 48000, SampleFormat::F32),
+        config_range(2, 8000, 48000, SampleFormat::F32),
+    ];
+    let request = StreamConfigRequest { sample_rate: Some(44100), buffer_size: Some(512), channels: Some(2) };
+
+    let (config, buffer_size) = DeviceSource::select_config(&configs, &request).unwrap();
+    assert_eq!(config.channels(), 2);
+    assert_eq!(config.sample_rate().0, 44100);
+    assert_eq!(config.sample_format(), SampleFormat::F32);
+    assert_eq!(buffer_size, BufferSize::Fixed(512));
+}
+
+/// A rate and buffer size outside of every range are clamped to the closest supported values
+#[test]
+fn test_select_closest_sample_rate() {
+    let configs = [
+        config_range(2, 8000, 22050, SampleFormat::F32),
+        config_range(2, 44100, 48000, SampleFormat::F32),
+    ];
+    let request = StreamConfigRequest { sample_rate: Some(96000), buffer_size: Some(8192), channels: Some(2) };
+
+    let (config, buffer_size) = DeviceSource::select_config(&configs, &request).unwrap();
+    assert_eq!(config.sample_rate().0, 48000);
+    assert_eq!(buffer_size, BufferSize::Fixed(4096));
+
+    // Without a requested rate, the highest rate of the range is used
+    let request = StreamConfigRequest { sample_rate: None, buffer_size: None, channels: Some(2) };
+    let (config, buffer_size) = DeviceSource::select_config(&configs[..1], &request).unwrap();
+    assert_eq!(config.sample_rate().0, 22050);
+    assert_eq!(buffer_size, BufferSize::Default);
+}
+
+/// The closest channel count is preferred over a matching sample rate
+#[test]
+fn test_select_channel_fallback() {
+    let configs = [
+        config_range(1, 44100, 44100, SampleFormat::F32),
+        config_range(4, 48000, 48000, SampleFormat::F32),
+        config_range(8, 44100, 44100, SampleFormat::F32),
+    ];
+    let request = StreamConfigRequest { sample_rate: Some(44100), buffer_size: None, channels: Some(3) };
+
+    let (config, _) = DeviceSource::select_config(&configs, &request).unwrap();
+    assert_eq!(config.channels(), 4);
+    assert_eq!(config.sample_rate().0, 48000);
+}
+
+/// With equal channels and rates, the preferred sample format is selected and unsupported formats are skipped
+#[test]
+fn test_select_format_preference() {
+    let configs = [
+        config_range(2, 44100, 48000, SampleFormat::I16),
+        config_range(2, 44100, 48000, SampleFormat::F32),
+        config_range(2, 44100, 48000, SampleFormat::I32),
+    ];
+    let request = StreamConfigRequest { sample_rate: Some(48000), buffer_size: None, channels: Some(2) };
+
+    let (config, _) = DeviceSource::select_config(&configs, &request).unwrap();
+    assert_eq!(config.sample_format(), SampleFormat::F32);
+
+    let (config, _) = DeviceSource::select_config(&[configs[0], configs[2]], &request).unwrap();
+    assert_eq!(config.sample_format(), SampleFormat::I32);
+
+    assert!(DeviceSource::select_config(&[], &request).is_none());
+}
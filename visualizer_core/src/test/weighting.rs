@@ -0,0 +1,57 @@
+use crate::dsp::{LoudnessWeighting, WeightingCache};
+
+/// The A-weighting must match the values of the standard
+#[test]
+fn test_a_weighting() {
+    let weighting = LoudnessWeighting::AWeighting;
+    for (frequency, expected) in [(100.0, -19.1), (1000.0, 0.0), (10000.0, -2.5)] {
+        let db = weighting.db(frequency);
+        assert!((db - expected).abs() < 0.1, "{}: {}", frequency, db);
+    }
+    assert_eq!(weighting.weights(&[0.0])[0], 0.0);
+}
+
+/// The equal-loudness contours must be 0 dB at 1 kHz and flatter for louder levels
+#[test]
+fn test_iso_226() {
+    let quiet = LoudnessWeighting::Iso226 { phon: 40.0 };
+    let loud = LoudnessWeighting::Iso226 { phon: 80.0 };
+
+    assert!(quiet.db(1000.0).abs() < 0.1);
+    assert!(loud.db(1000.0).abs() < 0.1);
+    // At 40 phon, 100 Hz needs about 24.4 dB more than 1 kHz
+    assert!((quiet.db(100.0) + 24.4).abs() < 0.5, "{}", quiet.db(100.0));
+    assert!(loud.db(100.0) > quiet.db(100.0));
+    // The ear is most sensitive around 3 to 4 kHz
+    assert!(quiet.db(3150.0) > 0.0);
+}
+
+/// Without a weighting, the spectrum must stay unchanged
+#[test]
+fn test_weighting_cache() {
+    let frequencies = [0.0, 100.0, 1000.0];
+    let mut cache = WeightingCache::new();
+
+    let mut spectrum = [1.0; 3];
+    cache.apply(&mut spectrum, &frequencies, LoudnessWeighting::None, 48000);
+    assert_eq!(spectrum, [1.0; 3]);
+
+    cache.apply(&mut spectrum, &frequencies, LoudnessWeighting::AWeighting, 48000);
+    assert_eq!(spectrum[0], 0.0);
+    assert!(spectrum[1] < 0.02);
+    assert!((spectrum[2] - 1.0).abs() < 0.01);
+}
+
+/// The note bins of the constant-Q transform move with the minimum frequency, so the cached weights must follow them
+#[test]
+fn test_weighting_cache_frequencies() {
+    let mut cache = WeightingCache::new();
+
+    let mut spectrum = [1.0; 2];
+    cache.apply(&mut spectrum, &[1000.0, 2000.0], LoudnessWeighting::AWeighting, 48000);
+    assert!((spectrum[0] - 1.0).abs() < 0.01);
+
+    let mut spectrum = [1.0; 2];
+    cache.apply(&mut spectrum, &[100.0, 200.0], LoudnessWeighting::AWeighting, 48000);
+    assert!(spectrum[0] < 0.02, "{:?}", spectrum);
+}
@@ -0,0 +1,36 @@
+use crate::dsp::WindowFunction;
+
+const WINDOW_FUNCTIONS: [WindowFunction; 6] = [
+    WindowFunction::Hann,
+    WindowFunction::Hamming,
+    WindowFunction::BlackmanHarris,
+    WindowFunction::Kaiser { beta: 8.6 },
+    WindowFunction::FlatTop,
+    WindowFunction::Rectangular,
+];
+
+/// After the amplitude correction every window must have a coherent gain of one
+#[test]
+fn test_window_amplitude_correction() {
+    for function in WINDOW_FUNCTIONS {
+        let window = function.create(1024);
+        let gain = window.iter().sum::<f32>() / window.len() as f32;
+
+        assert_eq!(window.len(), 1024);
+        assert!((gain - 1.0).abs() < 1e-4, "{:?} has a gain of {}", function, gain);
+    }
+}
+
+/// The windows must be symmetric and have their maximum in the center
+#[test]
+fn test_window_symmetry() {
+    for function in WINDOW_FUNCTIONS {
+        let window = function.create(513);
+        let max = window.iter().cloned().fold(f32::MIN, f32::max);
+
+        for n in 0..window.len() {
+            assert!((window[n] - window[window.len() - 1 - n]).abs() < 1e-4, "{:?} is not symmetric", function);
+        }
+        assert!((window[256] - max).abs() < 1e-4, "{:?} has no maximum in the center", function);
+    }
+}
@@ -4,7 +4,7 @@ mod view;
 mod view_model;
 mod utils;
 
-pub const APP_NAME: &'static str = "Audio Visualizer";
+pub const APP_NAME: &str = "Audio Visualizer";
 
 fn main() {
     env_logger::init();
@@ -52,7 +52,7 @@ impl StreamReader {
     }
 
     /// Get the last received frame.
-    pub fn lock_frame(&self) -> MutexGuard<Option<StreamFrame>> {
+    pub fn lock_frame(&self) -> MutexGuard<'_, Option<StreamFrame>> {
         self.frame_mutex.lock().expect("Failed to lock mutex. Has the thread panicked?")
     }
 
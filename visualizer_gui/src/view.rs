@@ -2,13 +2,14 @@ pub mod color_slider;
 mod utils;
 
 use crate::view::color_slider::color_slider;
-use crate::view::utils::settings_grid;
+use crate::view::utils::{named_combo_box, optional_combo_box, settings_grid};
 use crate::view_model::AudioVisualizerViewModel;
 use eframe::emath::Vec2b;
 use eframe::{App, Frame};
 use egui::ecolor::Hsva;
 use egui::{remap_clamp, Color32, Context, Ui};
 use egui_plot::Line;
+use visualizer_core::{ChannelMode, EqPoint, FilterNormalization, Filterbank, FrequencyScale, LoudnessWeighting, SmoothingMethod, WindowFunction};
 
 /// The App
 pub struct AudioVisualizerView {
@@ -60,7 +61,7 @@ impl AudioVisualizerView {
 
     fn show_window(&mut self, ui: &mut Ui) {
 
-        egui::Window::new("Settings").show(&ui.ctx(), |ui| {
+        egui::Window::new("Settings").show(ui.ctx(), |ui| {
             // Audio Source Settings
             settings_grid("grid_audio_source", ui, |ui| {
                 grid_audio_source(ui, &mut self.vm);
@@ -77,11 +78,10 @@ fn grid_audio_source(ui: &mut Ui, vm: &mut AudioVisualizerViewModel) {
         .selected_text(vm.get_selected_host())
         .show_ui(ui, |ui| {
             for (i, host) in vm.get_hosts().iter().enumerate() {
-                if ui.selectable_value(&mut vm.selected_host, i, host.name()).clicked() {
-                    if vm.selected_host != i {
-                        // Notify when another host was selected
-                        vm.click_update_host(host);
-                    }
+                if ui.selectable_value(&mut vm.selected_host, i, host.name()).clicked()
+                    && vm.selected_host != i {
+                    // Notify when another host was selected
+                    vm.click_update_host(host);
                 }
             }
         });
@@ -101,6 +101,28 @@ fn grid_audio_source(ui: &mut Ui, vm: &mut AudioVisualizerViewModel) {
         });
     ui.end_row();
 
+    ui.label("Sample rate");
+    if optional_combo_box(ui, "sample_rate", &mut vm.config_request.sample_rate, &[44100, 48000, 88200, 96000]) {
+        vm.click_update_stream_config();
+    }
+    ui.end_row();
+
+    ui.label("Buffer size");
+    if optional_combo_box(ui, "buffer_size", &mut vm.config_request.buffer_size, &[128, 256, 512, 1024, 2048]) {
+        vm.click_update_stream_config();
+    }
+    ui.end_row();
+
+    ui.label("Channels");
+    if optional_combo_box(ui, "channels", &mut vm.config_request.channels, &[1, 2]) {
+        vm.click_update_stream_config();
+    }
+    ui.end_row();
+
+    ui.label("Stream");
+    ui.label(vm.get_stream_description());
+    ui.end_row();
+
     ui.label("Effect");
     egui::ComboBox::from_id_salt("effect")
         .selected_text(vm.get_selected_effect())
@@ -136,6 +158,244 @@ fn grid_audio_source(ui: &mut Ui, vm: &mut AudioVisualizerViewModel) {
     }
     ui.end_row();
 
+    ui.label("Channel mode");
+    let channel_modes = [(ChannelMode::Downmix, "Downmix"), (ChannelMode::PerChannel, "Per channel")];
+    if named_combo_box(ui, "channel_mode", &mut vm.settings.channel_mode, &channel_modes) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Filterbank");
+    let filterbanks = [(Filterbank::Triangular, "Triangular"), (Filterbank::ConstantQ, "Constant-Q")];
+    if named_combo_box(ui, "filterbank", &mut vm.settings.filterbank, &filterbanks) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Bins per octave");
+    let bins_per_octave = [(12, "12"), (24, "24"), (36, "36")];
+    if named_combo_box(ui, "bins_per_octave", &mut vm.settings.bins_per_octave, &bins_per_octave) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Frequency scale");
+    // Keep the bands per octave, if the logarithmic scale is already selected
+    let bands_per_octave = match vm.settings.frequency_scale {
+        FrequencyScale::Logarithmic { bands_per_octave } => bands_per_octave,
+        _ => 3,
+    };
+    let scales = [
+        (FrequencyScale::Mel, "Mel"),
+        (FrequencyScale::Bark, "Bark"),
+        (FrequencyScale::Erb, "ERB"),
+        (FrequencyScale::Logarithmic { bands_per_octave }, "Logarithmic"),
+        (FrequencyScale::Linear, "Linear"),
+    ];
+    if named_combo_box(ui, "frequency_scale", &mut vm.settings.frequency_scale, &scales) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    if let FrequencyScale::Logarithmic { bands_per_octave } = &mut vm.settings.frequency_scale {
+        ui.label("Bands per octave");
+        let fractions = [(1, "1"), (2, "1/2"), (3, "1/3"), (6, "1/6"), (12, "1/12"), (24, "1/24")];
+        if named_combo_box(ui, "bands_per_octave", bands_per_octave, &fractions) {
+            vm.click_update_settings();
+        }
+        ui.end_row();
+
+        // The octave bands replace the amount of bins of the filterbank
+        let bands = vm.settings.frequency_scale.band_count(vm.settings.min_frequency as f32, vm.settings.max_frequency as f32);
+        ui.label("Filterbank bands");
+        ui.label(bands.unwrap_or(0).to_string());
+        ui.end_row();
+    }
+
+    ui.label("Filter normalization");
+    let normalizations = [
+        (FilterNormalization::None, "None"),
+        (FilterNormalization::Area, "Area"),
+        (FilterNormalization::Peak, "Peak"),
+    ];
+    if named_combo_box(ui, "filter_normalization", &mut vm.settings.filter_normalization, &normalizations) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    let frame_sizes = [(256, "256"), (512, "512"), (1024, "1024"), (2048, "2048"), (4096, "4096"), (8192, "8192")];
+    ui.label("Window size");
+    if named_combo_box(ui, "window_size", &mut vm.settings.window_size, &frame_sizes) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Hop size");
+    if named_combo_box(ui, "hop_size", &mut vm.settings.hop_size, &frame_sizes[..5]) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Bass window size");
+    let bass_window_sizes = [(None, "Off"), (Some(2048), "2048"), (Some(4096), "4096"), (Some(8192), "8192"), (Some(16384), "16384")];
+    if named_combo_box(ui, "bass_window_size", &mut vm.settings.bass_window_size, &bass_window_sizes) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Crossover frequency");
+    if ui.add(egui::Slider::new(&mut vm.settings.crossover_frequency, 50..=1000)).dragged() {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("FFT size");
+    let fft_sizes = [(1024, "1024"), (2048, "2048"), (4096, "4096"), (8192, "8192"), (16384, "16384")];
+    if named_combo_box(ui, "fft_size", &mut vm.settings.fft_size, &fft_sizes) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Window function");
+    // Keep the current beta, so the selected Kaiser window is found in the options
+    let beta = match vm.settings.window_function {
+        WindowFunction::Kaiser { beta } => beta,
+        _ => 8.6,
+    };
+    let window_functions = [
+        (WindowFunction::Hann, "Hann"),
+        (WindowFunction::Hamming, "Hamming"),
+        (WindowFunction::BlackmanHarris, "Blackman-Harris"),
+        (WindowFunction::Kaiser { beta }, "Kaiser"),
+        (WindowFunction::FlatTop, "Flat top"),
+        (WindowFunction::Rectangular, "Rectangular"),
+    ];
+    if named_combo_box(ui, "window_function", &mut vm.settings.window_function, &window_functions) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    if let WindowFunction::Kaiser { beta } = &mut vm.settings.window_function {
+        ui.label("Kaiser beta");
+        if ui.add(egui::Slider::new(beta, 0.0..=20.0)).dragged() {
+            vm.click_update_settings();
+        }
+        ui.end_row();
+    }
+
+    ui.label("Pre-emphasis");
+    if ui.add(egui::Slider::new(&mut vm.settings.pre_emphasis, 0.0..=0.99)).dragged() {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Loudness weighting");
+    // Keep the current loudness level, so the selected contour is found in the options
+    let phon = match vm.settings.loudness_weighting {
+        LoudnessWeighting::Iso226 { phon } => phon,
+        _ => 40.0,
+    };
+    let weightings = [
+        (LoudnessWeighting::None, "None"),
+        (LoudnessWeighting::AWeighting, "A-weighting"),
+        (LoudnessWeighting::Iso226 { phon }, "ISO 226"),
+    ];
+    if named_combo_box(ui, "loudness_weighting", &mut vm.settings.loudness_weighting, &weightings) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    if let LoudnessWeighting::Iso226 { phon } = &mut vm.settings.loudness_weighting {
+        ui.label("Loudness level (phon)");
+        if ui.add(egui::Slider::new(phon, 20.0..=90.0)).dragged() {
+            vm.click_update_settings();
+        }
+        ui.end_row();
+    }
+
+    ui.label("Smoothing");
+    let smoothing_methods = [
+        (SmoothingMethod::Exponential, "Exponential"),
+        (SmoothingMethod::OneEuro, "One-euro"),
+        (SmoothingMethod::Median, "Moving median"),
+        (SmoothingMethod::Kalman, "Kalman"),
+    ];
+    if named_combo_box(ui, "smoothing", &mut vm.settings.smoothing, &smoothing_methods) {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    // The points are edited on a copy, because the equalizer sorts them again after every change
+    let mut changed = None;
+    let mut removed = None;
+    for (i, mut point) in vm.settings.equalizer.points().to_vec().into_iter().enumerate() {
+        ui.label(format!("EQ point {}", i + 1));
+        ui.horizontal(|ui| {
+            let frequency = egui::DragValue::new(&mut point.frequency)
+                .range(20.0..=20000.0)
+                .suffix(" Hz");
+            let frequency_changed = ui.add(frequency).changed();
+            let gain_changed = ui.add(egui::Slider::new(&mut point.gain, -24.0..=24.0).suffix(" dB")).changed();
+            if frequency_changed || gain_changed {
+                changed = Some((i, point));
+            }
+            if ui.button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+        ui.end_row();
+    }
+    if let Some((i, point)) = changed {
+        vm.settings.equalizer.set_point(i, point);
+        vm.click_update_settings();
+    }
+    if let Some(i) = removed {
+        vm.settings.equalizer.remove_point(i);
+        vm.click_update_settings();
+    }
+    ui.label("");
+    if ui.button("Add EQ point").clicked() {
+        vm.settings.equalizer.add_point(EqPoint { frequency: 1000.0, gain: 0.0 });
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Noise profile");
+    ui.horizontal(|ui| {
+        ui.label(vm.get_noise_description());
+        if ui.button("Learn").clicked() {
+            vm.click_learn_noise();
+        }
+        if ui.button("Clear").clicked() {
+            vm.click_clear_noise();
+        }
+    });
+    ui.end_row();
+
+    ui.label("Noise floor (dBFS)");
+    if ui.add(egui::Slider::new(&mut vm.settings.agc.noise_floor, -100.0..=-20.0)).dragged() {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Gate hysteresis (dB)");
+    if ui.add(egui::Slider::new(&mut vm.settings.agc.hysteresis, 0.0..=20.0)).dragged() {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Gate hold time (ms)");
+    if ui.add(egui::Slider::new(&mut vm.settings.agc.hold_time, 0.0..=2000.0)).dragged() {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
+    ui.label("Max gain (dB)");
+    if ui.add(egui::Slider::new(&mut vm.settings.agc.max_gain, 0.0..=60.0)).dragged() {
+        vm.click_update_settings();
+    }
+    ui.end_row();
+
 
     if !vm.color_selection_enabled {
         ui.disable()
@@ -102,11 +102,10 @@ pub fn color_slider(
 
         let mut text = value.to_string();
         let text_response = ui.add(TextEdit::singleline(&mut text).desired_width(30.0));
-        if text_response.changed() {
-            if let Ok(v) = text.parse::<u16>() {
-                *value = v.clamp(*range.start(), *range.end());
-                changed = true
-            }
+        if text_response.changed()
+            && let Ok(v) = text.parse::<u16>() {
+            *value = v.clamp(*range.start(), *range.end());
+            changed = true
         }
 
         response
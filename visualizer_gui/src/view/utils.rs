@@ -1,3 +1,4 @@
+use std::fmt::Display;
 use egui::{InnerResponse, Ui};
 
 /// Shortcut to build a settings grid
@@ -9,4 +10,45 @@ pub fn settings_grid<R>(id: &'static str, ui: &mut Ui, content: impl FnOnce(&mut
         .show(ui, |ui| {
             content(ui)
         })
-}
\ No newline at end of file
+}
+
+/// ComboBox to choose an optional value. None is shown as the default value.
+/// Returns true, if another value was selected
+pub fn optional_combo_box<T: Copy + PartialEq + Display>(ui: &mut Ui, id: &'static str, value: &mut Option<T>, options: &[T]) -> bool {
+    let text = |value: &Option<T>| value.map_or(String::from("Default"), |it| it.to_string());
+    let mut changed = false;
+
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(text(value))
+        .show_ui(ui, |ui| {
+            let choices = std::iter::once(None).chain(options.iter().copied().map(Some));
+            for choice in choices {
+                if ui.selectable_value(value, choice, text(&choice)).clicked() {
+                    changed = true;
+                }
+            }
+        });
+
+    changed
+}
+
+/// ComboBox to choose one of the given values with their names.
+/// Returns true, if another value was selected
+pub fn named_combo_box<T: Copy + PartialEq>(ui: &mut Ui, id: &'static str, value: &mut T, options: &[(T, &str)]) -> bool {
+    let selected = options.iter()
+        .find(|(option, _)| option == value)
+        .map_or("", |(_, name)| *name);
+    let mut changed = false;
+
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (option, name) in options {
+                if ui.selectable_value(value, *option, *name).clicked() {
+                    changed = true;
+                }
+            }
+        });
+
+    changed
+}
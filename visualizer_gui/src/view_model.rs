@@ -2,10 +2,11 @@ use egui::ecolor::Hsva;
 use egui::{remap_clamp, Color32};
 use egui_plot::{PlotBounds, PlotPoints};
 use std::ops::Deref;
+use std::time::Duration;
 
 use super::view::color_slider::ColorState;
 use super::utils::{MapToPlotPoints, StreamReader};
-use visualizer_core::{Controller, HostId, InputDevice, Settings};
+use visualizer_core::{Controller, HostId, InputDevice, Settings, StreamConfigRequest, StreamInfo};
 
 
 pub struct AudioVisualizerViewModel {
@@ -20,8 +21,10 @@ pub struct AudioVisualizerViewModel {
     pub selected_effect: usize,
     pub use_logarithmic_scale: bool,
     pub settings: Settings,
+    pub config_request: StreamConfigRequest,
     pub color: ColorState,
     pub color_selection_enabled: bool,
+    pub stream_info: Option<StreamInfo>,
 }
 
 pub struct PlotUpdate<'a> {
@@ -43,10 +46,11 @@ impl AudioVisualizerViewModel {
         let color = ColorState::default();
 
         // Open the stream
-        let rx = controller.update_stream(0, first_effect, settings, color.as_rgb()).unwrap();
+        let rx = controller.update_stream(0, first_effect, settings.clone(), color.as_rgb()).unwrap();
         // Start the reader and listen to the audio visualizer
         let mut stream_reader = StreamReader::new();
         stream_reader.start(rx);
+        let stream_info = controller.stream_info().ok();
 
         AudioVisualizerViewModel {
             controller,
@@ -59,8 +63,10 @@ impl AudioVisualizerViewModel {
             selected_effect: 0,
             use_logarithmic_scale: false,
             settings,
+            config_request: StreamConfigRequest::default(),
             color,
             color_selection_enabled: true,
+            stream_info,
         }
     }
 
@@ -88,6 +94,16 @@ impl AudioVisualizerViewModel {
         self.effects[self.selected_effect]
     }
 
+    pub fn get_stream_description(&self) -> String {
+        match self.stream_info {
+            Some(info) => {
+                let buffer_size = info.buffer_size.map_or(String::from("default"), |it| it.to_string());
+                format!("{} Hz, {} channels, {}, {} frames", info.sample_rate, info.channels, info.sample_format, buffer_size)
+            }
+            None => String::from("No stream"),
+        }
+    }
+
     pub fn click_update_host(&mut self, host: &HostId) {
         //Update lib if the host was clicked and load new input devices
         self.controller.change_host(*host).unwrap();
@@ -98,11 +114,19 @@ impl AudioVisualizerViewModel {
     pub fn click_update_controller(&mut self, device: &InputDevice) {
         // Update the device inside the lib and update the stream
 
-        if let Ok(rx) = self.controller.update_stream(device.id, self.effects[self.selected_effect], self.settings, self.color.as_rgb()) {
-            self.stream_reader.start(rx)
+        if let Ok(rx) = self.controller.update_stream(device.id, self.effects[self.selected_effect], self.settings.clone(), self.color.as_rgb()) {
+            self.stream_reader.start(rx);
+            self.stream_info = self.controller.stream_info().ok();
         }
     }
 
+    pub fn click_update_stream_config(&mut self) {
+        // Reopen the stream on the current device with the new config
+        self.controller.change_stream_config(self.config_request);
+        let device = self.devices[self.selected_device].clone();
+        self.click_update_controller(&device);
+    }
+
     pub fn click_update_color(&mut self) {
         let hue = remap_clamp(
             self.color.hue as f32,
@@ -121,7 +145,28 @@ impl AudioVisualizerViewModel {
     }
 
     pub fn click_update_settings(&mut self) {
-        self.controller.update_stream_settings(self.settings)
+        self.controller.update_stream_settings(self.settings.clone())
+    }
+
+    /// The time, which the noise of the input is recorded
+    const NOISE_LEARNING_TIME: Duration = Duration::from_secs(3);
+
+    pub fn get_noise_description(&self) -> &'static str {
+        if self.controller.is_learning_noise() {
+            "Learning..."
+        } else if self.controller.has_noise_profile() {
+            "Learned"
+        } else {
+            "No profile"
+        }
+    }
+
+    pub fn click_learn_noise(&mut self) {
+        let _ = self.controller.learn_noise(Self::NOISE_LEARNING_TIME);
+    }
+
+    pub fn click_clear_noise(&mut self) {
+        self.controller.clear_noise_profile()
     }
 
     pub fn click_update_effect(&mut self) {
@@ -132,7 +177,7 @@ impl AudioVisualizerViewModel {
         }
     }
 
-    pub fn receive_plot_update(&self) -> Option<PlotUpdate> {
+    pub fn receive_plot_update(&self) -> Option<PlotUpdate<'_>> {
         // Receive data
         let guard = self.stream_reader.lock_frame();
         if let Some(frame) = guard.deref() {